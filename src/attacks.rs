@@ -0,0 +1,103 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Attack and defence maps of a position, E.G.
+// "Knight Felix3 attacks Eva5 and Gustav5; threatens Pawn Eva5; defended by Pawn Gustav2".
+
+use super::verbosity::Heading;
+use super::{join_with_and, PositionConverter};
+use std::fmt::Write;
+use tanton::board::Board;
+use tanton::core::sq::SQ;
+use tanton::core::{PieceType, Player};
+
+// Pieces in the same order as they are listed in the position description.
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::P,
+    PieceType::N,
+    PieceType::B,
+    PieceType::R,
+    PieceType::Q,
+    PieceType::K,
+];
+
+impl PositionConverter {
+    pub(crate) fn describe_attacks(&mut self, description: &mut String, board: &Board) {
+        self.write_heading(description, &self.describe_heading(Heading::Attacks));
+        // The side to move is described first.
+        for player in [board.turn(), !board.turn()].iter() {
+            let side = self.get_player_name(*player);
+            self.write_heading(description, &self.describe_heading(Heading::Pieces(side)));
+            for piece_type in PIECE_TYPES.iter() {
+                for square in board.piece_bb(*player, *piece_type) {
+                    writeln!(
+                        description,
+                        "{}",
+                        self.describe_piece_attacks(board, *player, *piece_type, square)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    fn describe_piece_attacks(
+        &self,
+        board: &Board,
+        player: Player,
+        piece_type: PieceType,
+        square: SQ,
+    ) -> String {
+        let mut piece_attacks = self.describe_piece(board, square);
+        // Squares holding pieces of the same side are defended rather than attacked.
+        let attacked =
+            board.attacks_from(piece_type, square, player) & !board.get_occupied_player(player);
+        if attacked.is_empty() {
+            write!(piece_attacks, " has no attacks").unwrap();
+        } else {
+            let squares: Vec<String> = attacked
+                .map(|attacked_square| self.describe_square(&attacked_square))
+                .collect();
+            write!(piece_attacks, " attacks {}", join_with_and(&squares)).unwrap();
+        }
+        let threatened: Vec<String> = (attacked & board.get_occupied_player(!player))
+            .map(|threatened_square| self.describe_piece(board, threatened_square))
+            .collect();
+        if !threatened.is_empty() {
+            write!(piece_attacks, "; threatens {}", join_with_and(&threatened)).unwrap();
+        }
+        if piece_type != PieceType::K {
+            let defenders: Vec<String> = (board.attackers_to(square, board.occupied())
+                & board.get_occupied_player(player))
+            .map(|defender_square| self.describe_piece(board, defender_square))
+            .collect();
+            if !defenders.is_empty() {
+                write!(piece_attacks, "; defended by {}", join_with_and(&defenders)).unwrap();
+            }
+        }
+        piece_attacks
+    }
+
+    // Describes the piece standing on |square| without its colour, E.G. "Knight Felix3".
+    fn describe_piece(&self, board: &Board, square: SQ) -> String {
+        let piece_type = board.piece_at_sq(square).type_of();
+        format!(
+            "{} {}",
+            self.get_piece_name(&piece_type.char_lower().to_string()),
+            self.describe_square(&square)
+        )
+    }
+}
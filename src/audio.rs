@@ -0,0 +1,157 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Turns converted exercises into audio files with a local text-to-speech command, E.G. espeak-ng or piper,
+// and writes an M3U playlist with one track per exercise, E.G. "exercise-003.wav".
+
+use super::ConvertedExercise;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Replaced in the arguments of the text-to-speech command by the path of the audio file to write.
+const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+pub struct AudioExporter {
+    // The text-to-speech program, which reads the text of a track on its standard input.
+    program: String,
+    // Arguments of |program|. If none contains |OUTPUT_PLACEHOLDER|, the audio is read from its standard output.
+    args: Vec<String>,
+    // Extension of the audio files, E.G. "wav" or "ogg". It should match what |program| writes.
+    extension: String,
+    // Whether solutions go in their own track after each exercise, so students can pause before hearing them.
+    separate_solutions: bool,
+}
+
+impl AudioExporter {
+    pub fn new(program: &str, args: &[&str]) -> AudioExporter {
+        AudioExporter {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extension: String::from("wav"),
+            separate_solutions: false,
+        }
+    }
+
+    pub fn set_extension(&mut self, extension: &str) {
+        self.extension = extension.to_string();
+    }
+
+    pub fn set_separate_solutions(&mut self, separate_solutions: bool) {
+        self.separate_solutions = separate_solutions;
+    }
+
+    // Writes the audio of each of |exercises|, as converted to text or SSML, next to |playlist_path|,
+    // and the playlist listing them in order. Tracks keep the numbers of the exercises, E.G. "exercise-003.wav".
+    pub fn export(&self, exercises: &[ConvertedExercise], playlist_path: &Path) -> io::Result<()> {
+        let directory = playlist_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut playlist = String::from("#EXTM3U\n");
+        for exercise in exercises.iter() {
+            let name = format!("exercise-{:03}", exercise.number);
+            let title = format!("Exercise {}", exercise.number);
+            let (position, solution) = if self.separate_solutions {
+                split_solution(exercise)
+            } else {
+                (exercise.description.clone(), None)
+            };
+            self.add_track(&mut playlist, directory, &name, &title, &position)?;
+            if let Some(solution) = solution {
+                let name = format!("{}-solution", name);
+                let title = format!("{}, solution", title);
+                self.add_track(&mut playlist, directory, &name, &title, &solution)?;
+            }
+        }
+        File::create(playlist_path)?.write_all(playlist.as_bytes())
+    }
+
+    fn add_track(
+        &self,
+        playlist: &mut String,
+        directory: &Path,
+        name: &str,
+        title: &str,
+        text: &str,
+    ) -> io::Result<()> {
+        let file_name = format!("{}.{}", name, self.extension);
+        self.speak(text, &directory.join(&file_name))?;
+        playlist.push_str(&format!("#EXTINF:-1,{}\n{}\n", title, file_name));
+        Ok(())
+    }
+
+    // Runs the text-to-speech command on |text| and makes sure |audio_path| was written.
+    fn speak(&self, text: &str, audio_path: &Path) -> io::Result<()> {
+        let audio_path_str = audio_path.to_string_lossy();
+        let writes_file = self.args.iter().any(|arg| arg.contains(OUTPUT_PLACEHOLDER));
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| arg.replace(OUTPUT_PLACEHOLDER, &audio_path_str))
+            .collect();
+        let stdout = if writes_file {
+            Stdio::null()
+        } else {
+            Stdio::from(File::create(audio_path)?)
+        };
+        let mut process = Command::new(&self.program)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(stdout)
+            .stderr(Stdio::null())
+            .spawn()?;
+        process.stdin.take().unwrap().write_all(text.as_bytes())?;
+        let status = process.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} failed with {} while writing {}",
+                self.program, status, audio_path_str
+            )));
+        }
+        if !audio_path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} did not write {}", self.program, audio_path_str),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Splits |exercise| into its position and its solution, if any, keeping SSML documents well formed.
+pub(crate) fn split_solution(exercise: &ConvertedExercise) -> (String, Option<String>) {
+    let description = &exercise.description;
+    let solution_line = match exercise.solution_line {
+        None => return (description.clone(), None),
+        Some(solution_line) => solution_line,
+    };
+    let start: usize = description
+        .split_inclusive('\n')
+        .take(solution_line)
+        .map(|line| line.len())
+        .sum();
+    if description.starts_with("<speak>") {
+        (
+            format!("{}</speak>\n", &description[..start]),
+            Some(format!("<speak>\n{}", &description[start..])),
+        )
+    } else {
+        (
+            description[..start].to_string(),
+            Some(description[start..].to_string()),
+        )
+    }
+}
@@ -0,0 +1,193 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// A terse rendering for braille displays: pieces are letters, squares are coordinates, moves are SAN, E.G.
+// "K h8" instead of "King Hector8", and the pieces of each side are laid out as a table, one row per piece type.
+
+use super::PositionConverter;
+use pgn_reader::San;
+use std::collections::HashMap;
+use std::fmt::Write;
+use tanton::core::sq::SQ;
+
+// The characters of North American Braille ASCII, in the order of the Unicode braille patterns they stand for,
+// so the character at index N is the cell U+2800 + N.
+const BRAILLE_ASCII: &str = " A1B'K2L@CIF/MSP\"E3H9O6R^DJG>NTQ,*5<-U8V.%[$+X!&;:4\\0Z7(_?W]#Y)=";
+
+// Lines per page of BRF files, the usual page length of embossers.
+const BRF_PAGE_LINES: usize = 25;
+
+// How the braille rendering is written out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrailleFormat {
+    // Plain text, for displays driven by a screen reader that does its own braille translation.
+    Text,
+    // Unicode braille patterns, one cell per character, in computer braille.
+    Unicode,
+    // Formatted braille files for embossers and notetakers, in computer braille with page breaks.
+    Brf,
+}
+
+impl PositionConverter {
+    pub(crate) fn get_braille_piece_name(&self, piece: &str) -> &'static str {
+        match piece {
+            "p" => "P",
+            "n" => "N",
+            "b" => "B",
+            "r" => "R",
+            "q" => "Q",
+            "k" => "K",
+            _ => "?",
+        }
+    }
+
+    pub(crate) fn get_braille_file_name(&self, file_number: u8) -> &'static str {
+        match file_number {
+            0 => "a",
+            1 => "b",
+            2 => "c",
+            3 => "d",
+            4 => "e",
+            5 => "f",
+            6 => "g",
+            7 => "h",
+            _ => "?",
+        }
+    }
+
+    pub(crate) fn describe_braille_san(&self, san: &San) -> String {
+        san.to_string()
+    }
+
+    // Writes one row per piece type, E.G. "P f2 g2 h2", with the most valuable pieces first.
+    pub(crate) fn describe_braille_pieces(
+        &self,
+        description: &mut String,
+        pieces: &str,
+        piece_to_squares: &HashMap<char, Vec<SQ>>,
+    ) {
+        for piece_with_color in pieces.chars().rev() {
+            if let Some(squares) = piece_to_squares.get(&piece_with_color) {
+                let piece = piece_with_color.to_lowercase().to_string();
+                let square_names: Vec<String> = squares
+                    .iter()
+                    .map(|square| self.describe_square(square))
+                    .collect();
+                writeln!(
+                    description,
+                    "{} {}",
+                    self.get_braille_piece_name(&piece),
+                    square_names.join(" ")
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    // Wraps |description| to the configured line width and writes it in the configured braille format.
+    pub(crate) fn format_braille(&self, description: &str) -> String {
+        let lines = wrap_lines(description, self.braille_line_width);
+        match self.braille {
+            Some(BrailleFormat::Unicode) => lines
+                .iter()
+                .map(|line| format!("{}\n", to_unicode_braille(line)))
+                .collect(),
+            Some(BrailleFormat::Brf) => {
+                let mut brf = String::new();
+                for (index, line) in lines.iter().enumerate() {
+                    if index > 0 && index % BRF_PAGE_LINES == 0 {
+                        brf.push('\x0c');
+                    }
+                    write!(brf, "{}\r\n", to_braille_ascii(line)).unwrap();
+                }
+                brf
+            }
+            _ => lines.iter().map(|line| format!("{}\n", line)).collect(),
+        }
+    }
+}
+
+// Wraps lines longer than |width| cells at spaces. Continuation lines are indented by two cells,
+// like runover lines in braille, and words that do not fit at all are split.
+fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(4);
+    let mut lines: Vec<String> = vec![];
+    for text_line in text.lines() {
+        let first_line_index = lines.len();
+        let mut line = String::new();
+        for word in text_line.split(' ') {
+            let mut word = word.to_string();
+            loop {
+                let separator = if line.trim().is_empty() { 0 } else { 1 };
+                if line.chars().count() + separator + word.chars().count() <= width {
+                    if separator == 1 {
+                        line.push(' ');
+                    }
+                    line.push_str(&word);
+                    break;
+                }
+                if line.trim().is_empty() {
+                    // The word alone is too long for a line.
+                    let room = width - line.chars().count();
+                    let head: String = word.chars().take(room).collect();
+                    line.push_str(&head);
+                    word = word.chars().skip(room).collect();
+                }
+                lines.push(std::mem::take(&mut line));
+                line.push_str("  ");
+                if word.is_empty() {
+                    break;
+                }
+            }
+        }
+        // A blank line is kept, but not the indent left after splitting a word at the end of a line.
+        if !line.trim().is_empty() || lines.len() == first_line_index {
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines
+}
+
+// Converts text to Braille ASCII, the character set of BRF files. Characters without a cell become "?".
+fn to_braille_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let c = match c {
+                '`' => '@',
+                '{' => '[',
+                '|' => '\\',
+                '}' => ']',
+                '~' => '^',
+                c => c.to_ascii_uppercase(),
+            };
+            if BRAILLE_ASCII.contains(c) {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+fn to_unicode_braille(text: &str) -> String {
+    to_braille_ascii(text)
+        .chars()
+        .map(|c| {
+            let cell = BRAILLE_ASCII.find(c).unwrap() as u32;
+            std::char::from_u32(0x2800 + cell).unwrap()
+        })
+        .collect()
+}
@@ -0,0 +1,199 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Cleans up pgn comments before they are read out. Embedded commands, E.G. "[%clk 0:03:12]" or "[%cal Ge2e4]",
+// are described in words or dropped, and moves written in SAN are described like the moves of the solution,
+// E.G. "Nf3 is better" becomes "Knight Felix3 is better".
+
+use super::engine::EngineScore;
+use super::PositionConverter;
+use pgn_reader::SanPlus;
+
+impl PositionConverter {
+    // Returns an empty string if nothing is left to read out, E.G. for a comment with only a clock time.
+    pub(crate) fn describe_comment(&self, comment: &str) -> String {
+        let mut sentences: Vec<String> = vec![];
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let end = match rest[start..].find(']') {
+                Some(end) => start + end,
+                None => break,
+            };
+            sentences.push(self.describe_comment_text(&rest[..start]));
+            if let Some(command) = self.describe_comment_command(&rest[start + 2..end]) {
+                sentences.push(format!("{}.", command));
+            }
+            rest = &rest[end + 1..];
+        }
+        sentences.push(self.describe_comment_text(rest));
+        sentences.retain(|sentence| !sentence.is_empty());
+        sentences.join(" ")
+    }
+
+    // Describes the moves in SAN of |text|, keeping everything else, E.G. "12. Nxe5!" becomes "12. Knight takes Eva5!".
+    fn describe_comment_text(&self, text: &str) -> String {
+        let mut description = String::new();
+        for word in text.split_inclusive(char::is_whitespace) {
+            let token = word.trim_end();
+            description.push_str(&self.describe_comment_token(token));
+            description.push_str(&word[token.len()..]);
+        }
+        description.trim().to_string()
+    }
+
+    fn describe_comment_token(&self, token: &str) -> String {
+        let core = token.trim_start_matches('(');
+        let core = core.trim_end_matches(|c: char| ",.;:!?)".contains(c));
+        // Move numbers can be glued to the move, E.G. "12.Nf3" or "12...Nf3".
+        let san = core.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let is_move = !san.is_empty()
+            && (san.starts_with("O-O") || san.contains(|c: char| c.is_ascii_digit()));
+        let san_plus = match SanPlus::from_ascii(san.as_bytes()) {
+            Ok(san_plus) if is_move => san_plus,
+            _ => return token.to_string(),
+        };
+        let start = token.len() - token.trim_start_matches('(').len() + (core.len() - san.len());
+        let end = start + san.len();
+        // Keeps the move number apart from the move, so it is not read as part of it.
+        let separator = if san.len() < core.len() { " " } else { "" };
+        format!(
+            "{}{}{}{}",
+            &token[..start],
+            separator,
+            self.describe_san_plus(&san_plus),
+            &token[end..]
+        )
+    }
+
+    pub(crate) fn describe_san_plus(&self, san_plus: &SanPlus) -> String {
+        let san_plus_text = san_plus.to_string();
+        if self.braille.is_some() {
+            return san_plus_text;
+        }
+        let mut description = self.describe_san(&san_plus.san);
+        if san_plus_text.ends_with('#') {
+            description.push_str(" checkmate");
+        } else if san_plus_text.ends_with('+') {
+            description.push_str(" check");
+        }
+        description
+    }
+
+    // Describes an embedded command, E.G. "clk 0:03:12". Returns None for the commands that are dropped.
+    fn describe_comment_command(&self, command: &str) -> Option<String> {
+        let mut tokens = command.split_whitespace();
+        let name = tokens.next()?;
+        let argument = tokens.next().unwrap_or("");
+        match name {
+            "clk" => Some(format!("Time left: {}", self.describe_clock(argument)?)),
+            "eval" => Some(format!("Evaluation: {}", self.describe_eval(argument)?)),
+            "csl" => Some(format!(
+                "Highlighted squares: {}",
+                self.describe_markers(argument, 1)?
+            )),
+            "cal" => Some(format!("Arrows: {}", self.describe_markers(argument, 2)?)),
+            _ => None,
+        }
+    }
+
+    // Describes a clock time, E.G. "1:03:12" or "0:00:05.3", as "one hour, three minutes, 12 seconds".
+    fn describe_clock(&self, clock: &str) -> Option<String> {
+        let mut units: Vec<u64> = vec![];
+        for part in clock.split(':') {
+            // Tenths of seconds are too short to matter when read out.
+            let whole = part.split('.').next()?;
+            units.push(whole.parse().ok()?);
+        }
+        if units.is_empty() || units.len() > 3 {
+            return None;
+        }
+        while units.len() < 3 {
+            units.insert(0, 0);
+        }
+        let names = [
+            ("hour", "hours"),
+            ("minute", "minutes"),
+            ("second", "seconds"),
+        ];
+        let parts: Vec<String> = units
+            .iter()
+            .zip(names.iter())
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, (singular, plural))| {
+                let name = if *count == 1 { singular } else { plural };
+                format!("{} {}", self.get_count_name(*count as usize), name)
+            })
+            .collect();
+        if parts.is_empty() {
+            return Some(String::from("no time"));
+        }
+        Some(parts.join(", "))
+    }
+
+    // Describes an evaluation from White's point of view, E.G. "1.2", "-0.35" or "#-3".
+    fn describe_eval(&self, eval: &str) -> Option<String> {
+        // Some tools add the search depth, E.G. "0.17,23".
+        let eval = eval.split(',').next()?;
+        let score = match eval.strip_prefix('#') {
+            Some(moves) => EngineScore::Mate(moves.parse().ok()?),
+            None => EngineScore::Centipawns((eval.parse::<f64>().ok()? * 100.0).round() as i32),
+        };
+        Some(self.describe_score(score))
+    }
+
+    // Describes colored squares or arrows, E.G. "Gf3,Rd4" as "Felix3 in green, David4 in red" and "Ge2e4" as
+    // "Eva2 to Eva4 in green". |squares| is 1 for squares and 2 for arrows.
+    fn describe_markers(&self, markers: &str, squares: usize) -> Option<String> {
+        let mut descriptions: Vec<String> = vec![];
+        for marker in markers.split(',').filter(|marker| !marker.is_empty()) {
+            let color = match marker.get(..1)? {
+                "G" => "green",
+                "R" => "red",
+                "Y" => "yellow",
+                "B" => "blue",
+                _ => return None,
+            };
+            let coordinates = marker.get(1..)?;
+            if coordinates.len() != 2 * squares {
+                return None;
+            }
+            let square_names: Option<Vec<String>> = (0..squares)
+                .map(|index| self.describe_square_name(coordinates.get(2 * index..2 * index + 2)?))
+                .collect();
+            descriptions.push(format!("{} in {}", square_names?.join(" to "), color));
+        }
+        if descriptions.is_empty() {
+            return None;
+        }
+        Some(descriptions.join(", "))
+    }
+
+    // Describes a square written like "f3", E.G. "Felix3".
+    fn describe_square_name(&self, square: &str) -> Option<String> {
+        let bytes = square.as_bytes();
+        if bytes.len() != 2
+            || !(b'a'..=b'h').contains(&bytes[0])
+            || !(b'1'..=b'8').contains(&bytes[1])
+        {
+            return None;
+        }
+        Some(format!(
+            "{}{}",
+            self.get_file_name(bytes[0] - b'a'),
+            bytes[1] as char
+        ))
+    }
+}
@@ -0,0 +1,262 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Talks to a local chess engine through the UCI protocol, E.G. Stockfish, and describes its analysis:
+// "Engine: White is winning by about three pawns; best line: 1. Rook Gustav8 check King takes Gustav8".
+
+use super::PositionConverter;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tanton::board::Board;
+use tanton::core::Player;
+
+// How many plies of the engine's best line are described.
+const ENGINE_LINE_PLIES: usize = 8;
+// How long the engine may take to start, or to answer once it is told to stop searching.
+const ENGINE_REPLY_TIME: Duration = Duration::from_secs(5);
+
+// The evaluation of a position from the point of view of the side to move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineScore {
+    // Advantage in hundredths of a pawn.
+    Centipawns(i32),
+    // Moves until mate. Negative when the side to move gets mated.
+    Mate(i32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub score: EngineScore,
+    // The best line found by the engine, in UCI notation, E.G. ["g1g8", "h8g8"].
+    pub principal_variation: Vec<String>,
+}
+
+pub struct Engine {
+    process: Child,
+    input: ChildStdin,
+    // The lines written by the engine, read on their own thread so waiting for them can time out.
+    output: Receiver<String>,
+    // Search depth of each analysis, in plies.
+    depth: u32,
+    // How long an analysis may take before the engine is told to stop searching.
+    time_limit: Duration,
+}
+
+impl Engine {
+    // Launches |program| with |args| and waits until it is ready to analyse positions.
+    pub fn start(program: &str, args: &[&str], depth: u32) -> io::Result<Engine> {
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let input = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+        let (sender, output) = channel();
+        // The thread ends when the engine closes its output, which disconnects the channel.
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Engine {
+            process,
+            input,
+            output,
+            depth,
+            time_limit: Duration::from_secs(30),
+        };
+        engine.send("uci")?;
+        engine.wait_for("uciok")?;
+        engine.send("isready")?;
+        engine.wait_for("readyok")?;
+        Ok(engine)
+    }
+
+    pub fn set_time_limit(&mut self, time_limit: Duration) {
+        self.time_limit = time_limit;
+    }
+
+    pub fn analyse(&mut self, fen: &str) -> io::Result<Analysis> {
+        self.send("ucinewgame")?;
+        // Skips whatever is left of an earlier analysis that was cut short.
+        self.send("isready")?;
+        self.wait_for("readyok")?;
+        self.send(&format!("position fen {}", fen))?;
+        self.send(&format!("go depth {}", self.depth))?;
+        let mut analysis: Option<Analysis> = None;
+        let mut deadline = Instant::now() + self.time_limit;
+        let mut stopped = false;
+        loop {
+            let line = match self.read_line(deadline) {
+                Err(error) if error.kind() == io::ErrorKind::TimedOut && !stopped => {
+                    // The best line found so far is still worth describing.
+                    self.send("stop")?;
+                    stopped = true;
+                    deadline = Instant::now() + ENGINE_REPLY_TIME;
+                    continue;
+                }
+                line => line?,
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    if let Some(info) = parse_info(tokens) {
+                        analysis = Some(info);
+                    }
+                }
+                Some("bestmove") => break,
+                _ => {}
+            }
+        }
+        analysis.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "engine did not report a score with a best line",
+            )
+        })
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", command)?;
+        self.input.flush()
+    }
+
+    fn read_line(&mut self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.output
+            .recv_timeout(timeout)
+            .map_err(|error| match error {
+                RecvTimeoutError::Timeout => {
+                    io::Error::new(io::ErrorKind::TimedOut, "engine did not answer in time")
+                }
+                RecvTimeoutError::Disconnected => {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "engine closed its output")
+                }
+            })
+    }
+
+    fn wait_for(&mut self, expected: &str) -> io::Result<()> {
+        let deadline = Instant::now() + ENGINE_REPLY_TIME;
+        while self.read_line(deadline)?.trim() != expected {}
+        Ok(())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        // The engine may already be gone, in which case there is nothing left to clean up.
+        if self.send("quit").is_err() {
+            let _ = self.process.kill();
+        }
+        let _ = self.process.wait();
+    }
+}
+
+// Parses an "info" line, E.G. "info depth 10 score cp 310 nodes 1000 pv g1g8 h8g8".
+// Returns None for lines without both a score and a best line. A mated side to move has no best line.
+fn parse_info<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<Analysis> {
+    let mut score: Option<EngineScore> = None;
+    let mut principal_variation: Vec<String> = vec![];
+    while let Some(token) = tokens.next() {
+        match token {
+            "score" => {
+                let kind = tokens.next()?;
+                let value: i32 = tokens.next()?.parse().ok()?;
+                score = match kind {
+                    "cp" => Some(EngineScore::Centipawns(value)),
+                    "mate" => Some(EngineScore::Mate(value)),
+                    _ => None,
+                };
+            }
+            // The best line is always the last field of an info line.
+            "pv" => {
+                principal_variation = tokens.by_ref().map(String::from).collect();
+            }
+            _ => {}
+        }
+    }
+    if principal_variation.is_empty() && score != Some(EngineScore::Mate(0)) {
+        return None;
+    }
+    Some(Analysis {
+        score: score?,
+        principal_variation,
+    })
+}
+
+impl PositionConverter {
+    pub(crate) fn describe_analysis(&self, board: &Board, analysis: &Analysis) -> String {
+        // Scores are reported for the side to move, but are described from White's point of view.
+        let sign = match board.turn() {
+            Player::White => 1,
+            Player::Black => -1,
+        };
+        let score = match analysis.score {
+            EngineScore::Mate(moves) => EngineScore::Mate(moves * sign),
+            EngineScore::Centipawns(centipawns) => EngineScore::Centipawns(centipawns * sign),
+        };
+        let evaluation = self.describe_score(score);
+        if analysis.principal_variation.is_empty() {
+            return evaluation;
+        }
+        let line: Vec<String> = analysis
+            .principal_variation
+            .iter()
+            .take(ENGINE_LINE_PLIES)
+            .cloned()
+            .collect();
+        format!(
+            "{}; best line: {}",
+            evaluation,
+            self.describe_uci_line(board, &line)
+        )
+    }
+
+    // Describes |score| from White's point of view, E.G. "White is better by about two pawns".
+    pub(crate) fn describe_score(&self, score: EngineScore) -> String {
+        match score {
+            // The side to move is already mated, E.G. when an exercise starts after the mating move.
+            EngineScore::Mate(0) => String::from("checkmate on the board"),
+            EngineScore::Mate(moves) => {
+                let winner = if moves > 0 { "White" } else { "Black" };
+                format!("{} mates in {}", winner, moves.abs())
+            }
+            EngineScore::Centipawns(centipawns) => {
+                let leader = if centipawns > 0 { "White" } else { "Black" };
+                let pawns = (centipawns.abs() + 50) / 100;
+                let pawns_name = match pawns {
+                    1 => String::from("a pawn"),
+                    _ => format!("{} pawns", self.get_count_name(pawns as usize)),
+                };
+                match centipawns.abs() {
+                    0..=29 => String::from("the position is about equal"),
+                    30..=149 => format!("{} is slightly better", leader),
+                    150..=299 => format!("{} is better by about {}", leader, pawns_name),
+                    _ => format!("{} is winning by about {}", leader, pawns_name),
+                }
+            }
+        }
+    }
+}
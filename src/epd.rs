@@ -0,0 +1,142 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Reads EPD files and plain lists of FENs, one position per line, E.G.
+// 2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";
+// Each line goes through the same Visitor as a pgn game: the "bm" opcode becomes the solution, "am" the moves to avoid
+// and "id" the title.
+
+use super::{ConvertedExercise, PositionConverter};
+use pgn_reader::{RawHeader, SanPlus, Visitor};
+use std::io;
+use tanton::board::Board;
+
+impl PositionConverter {
+    pub fn convert_epd(&mut self, epd: &str) -> io::Result<Vec<ConvertedExercise>> {
+        let mut exercises: Vec<ConvertedExercise> = vec![];
+        for (line_number, line) in epd.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let exercise = self.convert_epd_position(line).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {} of the EPD file: {}", line_number + 1, error),
+                )
+            })?;
+            exercises.push(exercise);
+        }
+        Ok(exercises)
+    }
+
+    fn convert_epd_position(&mut self, line: &str) -> Result<ConvertedExercise, &'static str> {
+        let (fields, mut rest) = split_fields(line, 4);
+        if fields.len() < 4 {
+            return Err("expected at least 4 fields");
+        }
+        // Plain FENs end with the halfmove clock and fullmove number instead of opcodes.
+        let mut counters = String::from("0 1");
+        let (numbers, rest_after_numbers) = split_fields(rest, 2);
+        if numbers.len() == 2 && numbers.iter().all(|number| number.parse::<u32>().is_ok()) {
+            counters = numbers.join(" ");
+            rest = rest_after_numbers;
+        }
+        let operations = parse_operations(rest);
+        let get_operands = |opcode: &str| {
+            operations
+                .iter()
+                .find(|(name, _)| name == opcode)
+                .map(|(_, operands)| operands)
+        };
+        if let Some(halfmoves) = get_operands("hmvc").and_then(|operands| operands.first()) {
+            counters = format!("{} 1", halfmoves);
+        }
+        if let Some(fullmoves) = get_operands("fmvn").and_then(|operands| operands.first()) {
+            let halfmoves = counters.split(' ').next().unwrap_or("0").to_string();
+            counters = format!("{} {}", halfmoves, fullmoves);
+        }
+        let fen = format!("{} {}", fields[..4].join(" "), counters);
+        Board::from_fen(&fen).map_err(|_| "invalid position")?;
+        let mut best_moves: Vec<SanPlus> = vec![];
+        for best_move in get_operands("bm").into_iter().flatten() {
+            best_moves
+                .push(SanPlus::from_ascii(best_move.as_bytes()).map_err(|_| "invalid bm move")?);
+        }
+
+        self.begin_game();
+        self.begin_headers();
+        self.header(b"FEN", RawHeader(fen.as_bytes()));
+        self.exercise_title = get_operands("id").and_then(|operands| operands.first().cloned());
+        self.avoid_moves = get_operands("am").cloned().unwrap_or_default();
+        let _ = self.end_headers();
+        // The first best move is the solution, the others are written as side lines replacing it, like pgn does.
+        let mut best_moves = best_moves.into_iter();
+        if let Some(best_move) = best_moves.next() {
+            self.san(best_move);
+        }
+        for alternative in best_moves {
+            if !self.begin_variation().0 {
+                self.san(alternative);
+            }
+            // Skipped side lines end too, like in pgn.
+            self.end_variation();
+        }
+        let description = self.end_game();
+        Ok(self.get_converted_exercise(description).unwrap())
+    }
+}
+
+// Splits the first |count| whitespace separated fields of |text| from the rest, however many spaces are between them.
+fn split_fields(text: &str, count: usize) -> (Vec<&str>, &str) {
+    let mut fields: Vec<&str> = vec![];
+    let mut rest = text.trim_start();
+    while fields.len() < count && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    (fields, rest)
+}
+
+// Splits EPD operations like `bm Qg6; id "WAC.001";` into opcodes and their operands, without quotes.
+fn parse_operations(text: &str) -> Vec<(String, Vec<String>)> {
+    let mut operations: Vec<(String, Vec<String>)> = vec![];
+    let mut tokens: Vec<String> = vec![];
+    let mut token = String::new();
+    let mut in_quotes = false;
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                if !tokens.is_empty() {
+                    let opcode = tokens.remove(0);
+                    operations.push((opcode, std::mem::take(&mut tokens)));
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    operations
+}
@@ -0,0 +1,305 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Packages converted exercises as an EPUB 3 book for braille displays and DAISY players:
+// one chapter per exercise, with solutions in their own chapter reachable through links.
+// Besides the EPUB 3 navigation document, a DAISY NCX (toc.ncx) is included for older reading systems.
+
+use super::audio::split_solution;
+use super::zip::ZipWriter;
+use super::ConvertedExercise;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+pub struct EpubExporter {
+    title: String,
+    // Language of the book, as a BCP 47 tag, E.G. "en".
+    language: String,
+}
+
+// One exercise of the book, split into the parts laid out in different chapters.
+struct Chapter {
+    // The number of the exercise, which also names its file and its solution, E.G. "exercise-003.xhtml".
+    number: usize,
+    title: String,
+    position: String,
+    solution: Option<String>,
+    // The heading lines of |position| and |solution|, counted from 0 in each of them.
+    position_headings: Vec<usize>,
+    solution_headings: Vec<usize>,
+}
+
+impl EpubExporter {
+    pub fn new(title: &str) -> EpubExporter {
+        EpubExporter {
+            title: title.to_string(),
+            language: String::from("en"),
+        }
+    }
+
+    pub fn set_language(&mut self, language: &str) {
+        self.language = language.to_string();
+    }
+
+    // Writes the book with |exercises|, as converted to text, to |path|.
+    pub fn export(&self, exercises: &[ConvertedExercise], path: &Path) -> io::Result<()> {
+        File::create(path)?.write_all(&self.build(exercises))
+    }
+
+    pub fn build(&self, exercises: &[ConvertedExercise]) -> Vec<u8> {
+        let chapters: Vec<Chapter> = exercises.iter().map(chapter).collect();
+        let mut zip = ZipWriter::new();
+        // The mimetype must be the first file of the archive.
+        zip.add_file("mimetype", b"application/epub+zip");
+        zip.add_file("META-INF/container.xml", CONTAINER.as_bytes());
+        zip.add_file("EPUB/package.opf", self.package(&chapters).as_bytes());
+        zip.add_file("EPUB/nav.xhtml", self.navigation(&chapters).as_bytes());
+        zip.add_file("EPUB/toc.ncx", self.ncx(&chapters).as_bytes());
+        for chapter in chapters.iter() {
+            let mut body = format!(
+                "<section>\n<h1>{}</h1>\n{}",
+                escape_xml(&chapter.title),
+                paragraphs(&chapter.position, &chapter.position_headings, 2)
+            );
+            if chapter.solution.is_some() {
+                body.push_str(&format!(
+                    "<p><a href=\"solutions.xhtml#solution-{}\">Solution</a></p>\n",
+                    chapter.number
+                ));
+            }
+            body.push_str("</section>\n");
+            zip.add_file(
+                &format!("EPUB/{}", chapter_file(chapter.number)),
+                self.xhtml(&chapter.title, &body).as_bytes(),
+            );
+        }
+        zip.add_file(
+            "EPUB/solutions.xhtml",
+            self.xhtml("Solutions", &solutions(&chapters)).as_bytes(),
+        );
+        zip.finish()
+    }
+
+    fn package(&self, chapters: &[Chapter]) -> String {
+        let mut manifest = String::new();
+        let mut spine = String::new();
+        for chapter in chapters.iter() {
+            manifest.push_str(&format!(
+                "    <item id=\"exercise-{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n",
+                chapter.number,
+                chapter_file(chapter.number)
+            ));
+            spine.push_str(&format!(
+                "    <itemref idref=\"exercise-{}\"/>\n",
+                chapter.number
+            ));
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{language}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:blindfold-chess:{identifier:08x}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{language}</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+    <meta property="schema:accessMode">textual</meta>
+    <meta property="schema:accessModeSufficient">textual</meta>
+    <meta property="schema:accessibilityFeature">structuralNavigation</meta>
+    <meta property="schema:accessibilityFeature">tableOfContents</meta>
+    <meta property="schema:accessibilityHazard">none</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="ncx" href="toc.ncx" media-type="application/x-dtbncx+xml"/>
+{manifest}    <item id="solutions" href="solutions.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine toc="ncx">
+{spine}    <itemref idref="solutions"/>
+  </spine>
+</package>
+"#,
+            language = escape_xml(&self.language),
+            identifier = super::zip::crc32(self.title.as_bytes()),
+            // Fixed, like the dates inside the archive, so the same exercises always give the same book.
+            modified = "2020-01-01T00:00:00Z",
+            title = escape_xml(&self.title),
+            manifest = manifest,
+            spine = spine
+        )
+    }
+
+    fn navigation(&self, chapters: &[Chapter]) -> String {
+        let mut items = String::new();
+        for chapter in chapters.iter() {
+            items.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                chapter_file(chapter.number),
+                escape_xml(&chapter.title)
+            ));
+        }
+        items.push_str("<li><a href=\"solutions.xhtml\">Solutions</a></li>\n");
+        let body = format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n<ol>\n{}</ol>\n</nav>\n",
+            items
+        );
+        self.xhtml("Contents", &body)
+    }
+
+    // The navigation control file of DAISY books, used by DAISY players and EPUB 2 reading systems.
+    fn ncx(&self, chapters: &[Chapter]) -> String {
+        let mut nav_points = String::new();
+        let mut add_nav_point = |label: &str, source: &str| {
+            let order = nav_points.matches("<navPoint ").count() + 1;
+            nav_points.push_str(&format!(
+                "    <navPoint id=\"nav-{0}\" playOrder=\"{0}\"><navLabel><text>{1}</text></navLabel><content src=\"{2}\"/></navPoint>\n",
+                order,
+                escape_xml(label),
+                source
+            ));
+        };
+        for chapter in chapters.iter() {
+            add_nav_point(&chapter.title, &chapter_file(chapter.number));
+        }
+        add_nav_point("Solutions", "solutions.xhtml");
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1" xml:lang="{language}">
+  <head>
+    <meta name="dtb:uid" content="urn:blindfold-chess:{identifier:08x}"/>
+    <meta name="dtb:depth" content="1"/>
+    <meta name="dtb:totalPageCount" content="0"/>
+    <meta name="dtb:maxPageNumber" content="0"/>
+  </head>
+  <docTitle><text>{title}</text></docTitle>
+  <navMap>
+{nav_points}  </navMap>
+</ncx>
+"#,
+            language = escape_xml(&self.language),
+            identifier = super::zip::crc32(self.title.as_bytes()),
+            title = escape_xml(&self.title),
+            nav_points = nav_points
+        )
+    }
+
+    fn xhtml(&self, title: &str, body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{language}" lang="{language}">
+<head>
+<title>{title}</title>
+</head>
+<body>
+{body}</body>
+</html>
+"#,
+            language = escape_xml(&self.language),
+            title = escape_xml(title),
+            body = body
+        )
+    }
+}
+
+// Splits |exercise| into the parts of its chapter. The first line, E.G. "Exercise 3: Mate in two:", is the title.
+fn chapter(exercise: &ConvertedExercise) -> Chapter {
+    let (position, solution) = split_solution(exercise);
+    let (title, position, title_lines) = match position.split_once('\n') {
+        Some((first_line, rest)) if first_line.starts_with("Exercise ") => (
+            first_line.trim_end_matches(':').to_string(),
+            rest.to_string(),
+            1,
+        ),
+        _ => (format!("Exercise {}", exercise.number), position, 0),
+    };
+    let solution_start = title_lines + position.lines().count();
+    Chapter {
+        number: exercise.number,
+        title,
+        position_headings: exercise
+            .heading_lines
+            .iter()
+            .filter(|line| (title_lines..solution_start).contains(line))
+            .map(|line| line - title_lines)
+            .collect(),
+        solution_headings: exercise
+            .heading_lines
+            .iter()
+            .filter(|line| **line >= solution_start)
+            .map(|line| line - solution_start)
+            .collect(),
+        position,
+        solution,
+    }
+}
+
+fn chapter_file(number: usize) -> String {
+    format!("exercise-{:03}.xhtml", number)
+}
+
+// Lays out the solutions of all exercises, each one linking back to its exercise.
+fn solutions(chapters: &[Chapter]) -> String {
+    let mut body = String::from("<h1>Solutions</h1>\n");
+    for chapter in chapters.iter() {
+        if let Some(solution) = &chapter.solution {
+            body.push_str(&format!(
+                "<section id=\"solution-{}\">\n<h2>{}</h2>\n{}<p><a href=\"{}\">Back to the exercise</a></p>\n</section>\n",
+                chapter.number,
+                escape_xml(&chapter.title),
+                paragraphs(solution, &chapter.solution_headings, 3),
+                chapter_file(chapter.number)
+            ));
+        }
+    }
+    body
+}
+
+// Turns description lines into paragraphs. The lines of |heading_lines|, E.G. "White:", become headings of
+// |heading_level| for the lines after them, so readers can jump between sections.
+fn paragraphs(text: &str, heading_lines: &[usize], heading_level: usize) -> String {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            if heading_lines.contains(&index) {
+                format!(
+                    "<h{0}>{1}</h{0}>\n",
+                    heading_level,
+                    escape_xml(line.trim_end_matches(':'))
+                )
+            } else {
+                format!("<p>{}</p>\n", escape_xml(line))
+            }
+        })
+        .collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="EPUB/package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
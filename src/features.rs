@@ -0,0 +1,204 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Key positional features of a position: pawn structure, open files, pins and hanging pieces.
+
+use super::material::is_passed_pawn;
+use super::verbosity::Heading;
+use super::PositionConverter;
+use std::fmt::Write;
+use tanton::board::Board;
+use tanton::core::sq::SQ;
+use tanton::core::{PieceType, Player};
+use tanton::helper::prelude::between_bb;
+
+impl PositionConverter {
+    pub(crate) fn describe_features(&mut self, description: &mut String, board: &Board) {
+        let mut features: Vec<String> = vec![];
+        for player in [Player::White, Player::Black].iter() {
+            self.describe_pawn_structure(&mut features, board, *player);
+        }
+        self.describe_files(&mut features, board);
+        self.describe_pins(&mut features, board);
+        self.describe_hanging_pieces(&mut features, board);
+        if features.is_empty() {
+            return;
+        }
+        self.write_heading(description, &self.describe_heading(Heading::Features));
+        for feature in features.iter() {
+            writeln!(description, "{}", feature).unwrap();
+        }
+    }
+
+    fn describe_pawn_structure(&self, features: &mut Vec<String>, board: &Board, player: Player) {
+        let side = self.get_player_name(player);
+        let pawns: Vec<SQ> = board.piece_bb(player, PieceType::P).collect();
+        let opponent_pawns: Vec<SQ> = board.piece_bb(!player, PieceType::P).collect();
+        let pawns_on_file = |file: i8| {
+            pawns
+                .iter()
+                .filter(|pawn| pawn.file_idx_of_sq() as i8 == file)
+                .count()
+        };
+        let isolated: Vec<&SQ> = pawns
+            .iter()
+            .filter(|pawn| {
+                let file = pawn.file_idx_of_sq() as i8;
+                pawns_on_file(file - 1) == 0 && pawns_on_file(file + 1) == 0
+            })
+            .collect();
+        let doubled: Vec<&SQ> = pawns
+            .iter()
+            .filter(|pawn| pawns_on_file(pawn.file_idx_of_sq() as i8) > 1)
+            .collect();
+        let passed: Vec<&SQ> = pawns
+            .iter()
+            .filter(|pawn| is_passed_pawn(**pawn, &opponent_pawns, player == Player::White))
+            .collect();
+        for (name, squares) in [
+            ("isolated", isolated),
+            ("doubled", doubled),
+            ("passed", passed),
+        ]
+        .iter()
+        {
+            if squares.is_empty() {
+                continue;
+            }
+            let square_names: Vec<String> = squares
+                .iter()
+                .map(|square| self.describe_square(square))
+                .collect();
+            features.push(format!(
+                "{} {} {}: {}",
+                side,
+                name,
+                if squares.len() == 1 { "pawn" } else { "pawns" },
+                square_names.join(", ")
+            ));
+        }
+    }
+
+    fn describe_files(&self, features: &mut Vec<String>, board: &Board) {
+        let has_pawn_on_file = |player: Player, file: u8| {
+            board
+                .piece_bb(player, PieceType::P)
+                .any(|pawn| pawn.file_idx_of_sq() == file)
+        };
+        let mut open: Vec<&str> = vec![];
+        let mut half_open_white: Vec<&str> = vec![];
+        let mut half_open_black: Vec<&str> = vec![];
+        for file in 0..8 {
+            match (
+                has_pawn_on_file(Player::White, file),
+                has_pawn_on_file(Player::Black, file),
+            ) {
+                (false, false) => open.push(self.get_file_name(file)),
+                (false, true) => half_open_white.push(self.get_file_name(file)),
+                (true, false) => half_open_black.push(self.get_file_name(file)),
+                (true, true) => {}
+            }
+        }
+        // A board without pawns has every file open, which is not worth mentioning.
+        if open.len() == 8 {
+            return;
+        }
+        if !open.is_empty() {
+            features.push(format!("Open files: {}", open.join(", ")));
+        }
+        if !half_open_white.is_empty() {
+            features.push(format!(
+                "Half-open files for White: {}",
+                half_open_white.join(", ")
+            ));
+        }
+        if !half_open_black.is_empty() {
+            features.push(format!(
+                "Half-open files for Black: {}",
+                half_open_black.join(", ")
+            ));
+        }
+    }
+
+    fn describe_pins(&self, features: &mut Vec<String>, board: &Board) {
+        for player in [Player::White, Player::Black].iter() {
+            let player = *player;
+            let king = board.king_sq(player);
+            for pinned in board.pinned_pieces(player) {
+                let pinner = board
+                    .sliding_piece_bb(!player)
+                    .chain(board.diagonal_piece_bb(!player))
+                    .find(|slider| {
+                        let between = between_bb(king, *slider);
+                        (between & board.occupied()) == pinned.to_bb()
+                            && (board.attacks_from(
+                                board.piece_at_sq(*slider).type_of(),
+                                *slider,
+                                !player,
+                            ) & pinned.to_bb())
+                            .is_not_empty()
+                    });
+                let mut feature = String::new();
+                write!(
+                    feature,
+                    "Pinned to the king: {}",
+                    self.describe_piece_on_square(board, pinned)
+                )
+                .unwrap();
+                if let Some(pinner) = pinner {
+                    write!(
+                        feature,
+                        " by {}",
+                        self.describe_piece_on_square(board, pinner)
+                    )
+                    .unwrap();
+                }
+                features.push(feature);
+            }
+        }
+    }
+
+    // A piece hangs when the opponent attacks it and no piece of its own side defends it.
+    fn describe_hanging_pieces(&self, features: &mut Vec<String>, board: &Board) {
+        let mut hanging: Vec<String> = vec![];
+        for player in [Player::White, Player::Black].iter() {
+            let player = *player;
+            let pieces = board.get_occupied_player(player) & !board.piece_bb(player, PieceType::K);
+            for square in pieces {
+                let attackers = board.attackers_to(square, board.occupied());
+                if (attackers & board.get_occupied_player(!player)).is_not_empty()
+                    && (attackers & board.get_occupied_player(player)).is_empty()
+                {
+                    hanging.push(self.describe_piece_on_square(board, square));
+                }
+            }
+        }
+        if !hanging.is_empty() {
+            features.push(format!("Hanging: {}", hanging.join(", ")));
+        }
+    }
+
+    // Describes the piece standing on |square| with its colour, E.G. "Black Knight Felix6".
+    fn describe_piece_on_square(&self, board: &Board, square: SQ) -> String {
+        let piece = board.piece_at_sq(square);
+        format!(
+            "{} {} {}",
+            self.get_player_name(piece.player_lossy()),
+            self.get_piece_name(&piece.type_of().char_lower().to_string()),
+            self.describe_square(&square)
+        )
+    }
+}
@@ -0,0 +1,101 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Describes puzzle metadata from the pgn headers, E.G. "Goal: mate in 3; Themes: fork, pin; Rating 1850".
+
+use super::PositionConverter;
+use pgn_reader::SanPlus;
+
+impl PositionConverter {
+    // Returns the exercise preamble built from known puzzle headers and the configured extra headers,
+    // or None if the exercise has none of them.
+    pub(crate) fn describe_puzzle_headers(&self) -> Option<String> {
+        let mut items: Vec<String> = vec![];
+        if let Some(goal) = self.get_header(&["Goal"]) {
+            items.push(format!("Goal: {}", lowercase_first(goal)));
+        }
+        if let Some(themes) = self.get_header(&["Themes", "PuzzleThemes"]) {
+            let themes: Vec<String> = themes
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|theme| !theme.is_empty())
+                .map(split_camel_case)
+                .collect();
+            items.push(format!("Themes: {}", themes.join(", ")));
+        }
+        if let Some(rating) = self.get_header(&["Rating", "PuzzleRating"]) {
+            items.push(format!("Rating {}", rating));
+        }
+        if !self.avoid_moves.is_empty() {
+            let avoid_moves: Vec<String> = self
+                .avoid_moves
+                .iter()
+                .map(
+                    |avoid_move| match SanPlus::from_ascii(avoid_move.as_bytes()) {
+                        Ok(san_plus) => self.describe_san_plus(&san_plus),
+                        Err(_) => avoid_move.to_string(),
+                    },
+                )
+                .collect();
+            items.push(format!("Avoid: {}", avoid_moves.join(" or ")));
+        }
+        if let Some(annotator) = self.get_header(&["Annotator"]) {
+            items.push(format!("Annotator: {}", annotator));
+        }
+        for extra_header in self.extra_headers.iter() {
+            if let Some(value) = self.get_header(&[&extra_header[..]]) {
+                items.push(format!("{}: {}", extra_header, value));
+            }
+        }
+        if items.is_empty() {
+            return None;
+        }
+        Some(items.join("; "))
+    }
+
+    // Returns the non empty value of the first header of the current exercise named like one of |keys|.
+    pub(crate) fn get_header(&self, keys: &[&str]) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, value)| keys.contains(&&key[..]) && !value.trim().is_empty())
+            .map(|(_, value)| value.trim())
+    }
+}
+
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Splits theme names as found in puzzle databases into words, E.G. "mateIn2" becomes "mate in 2".
+fn split_camel_case(theme: &str) -> String {
+    let mut words = String::new();
+    let mut previous: Option<char> = None;
+    for c in theme.chars() {
+        if let Some(previous) = previous {
+            let starts_word = (c.is_uppercase() && !previous.is_uppercase())
+                || (c.is_ascii_digit() && !previous.is_ascii_digit());
+            if starts_word {
+                words.push(' ');
+            }
+        }
+        words.extend(c.to_lowercase());
+        previous = Some(c);
+    }
+    words
+}
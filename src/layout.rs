@@ -0,0 +1,217 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Lays out the moves of a solution, E.G. two plies per line:
+// 1. Eva4 Eva5
+// 2. Knight Felix3
+// Moves are collected as they are parsed and only laid out once the whole solution is known.
+
+use super::{ssml, PositionConverter, Verbosity};
+
+// How the moves of a solution are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolutionLayout {
+    // One ply per line, E.G. "1. Eva4" and "1... Eva5".
+    PlyPerLine,
+    // A move of White and the answer of Black per line, E.G. "1. Eva4 Eva5".
+    PairPerLine,
+    // All moves in a single paragraph, E.G. "1. Eva4 Eva5 2. Knight Felix3".
+    Paragraph,
+}
+
+// How side lines are set apart from the line they branch from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VariationStyle {
+    // Marked with words, E.G. "Variation 1 for White's move 2:", "End of variation." and "Back to main line: 2...".
+    Spoken,
+    // Indented by two spaces per level of nesting.
+    Indented,
+}
+
+// What the solution is made of, in the order found in the pgn.
+pub(crate) enum SolutionItem {
+    // A move, already described, and its ply counted from 1. Odd plies are moves of White.
+    Move { ply: usize, description: String },
+    Comment(String),
+    // A side line replacing the move of |ply|.
+    VariationStart { ply: usize },
+    VariationEnd,
+}
+
+impl PositionConverter {
+    pub(crate) fn render_solution(&self, items: &[SolutionItem]) -> String {
+        let paragraph = self.solution_layout == SolutionLayout::Paragraph;
+        let indented = self.variation_style == VariationStyle::Indented;
+        let mut lines: Vec<String> = vec![];
+        let mut line: Vec<String> = vec![];
+        let mut depth: usize = 0;
+        // How many side lines branched so far from the last move of each line being laid out, from the main line.
+        let mut variation_counts: Vec<usize> = vec![0];
+        // Whether a side line just ended, so the next move says which line it goes back to.
+        let mut back_to_line = false;
+        // Whether the last thing written is a move of White, which the move of Black can follow without its number.
+        let mut after_white_move = false;
+        for item in items.iter() {
+            match item {
+                SolutionItem::Move { ply, description } => {
+                    let white = ply % 2 == 1;
+                    let starts_line = match self.solution_layout {
+                        SolutionLayout::PlyPerLine => true,
+                        SolutionLayout::PairPerLine => white || !after_white_move,
+                        SolutionLayout::Paragraph => false,
+                    };
+                    if starts_line || (back_to_line && !paragraph) {
+                        flush_line(&mut lines, &mut line, depth, indented);
+                    }
+                    if back_to_line && !indented {
+                        line.push(
+                            self.describe_marker(&format!("Back to {}:", describe_line(depth))),
+                        );
+                    }
+                    back_to_line = false;
+                    variation_counts.truncate(depth + 1);
+                    variation_counts[depth] = 0;
+                    let move_count = ply.div_ceil(2);
+                    if white
+                        || !after_white_move
+                        || self.solution_layout == SolutionLayout::PlyPerLine
+                    {
+                        line.push(self.describe_move_number(move_count, !white));
+                    }
+                    if self.with_side_prefixes {
+                        line.push(String::from(if white { "White:" } else { "Black:" }));
+                    }
+                    line.push(description.clone());
+                    after_white_move = white;
+                }
+                SolutionItem::Comment(comment) => {
+                    if !paragraph {
+                        flush_line(&mut lines, &mut line, depth, indented);
+                    }
+                    if self.ssml {
+                        line.push(ssml::ssml_lines(comment));
+                    } else {
+                        line.push(comment.trim().to_string());
+                    }
+                    if !paragraph {
+                        flush_line(&mut lines, &mut line, depth, indented);
+                    }
+                    after_white_move = false;
+                }
+                SolutionItem::VariationStart { ply } => {
+                    if !paragraph || indented {
+                        flush_line(&mut lines, &mut line, depth, indented);
+                    }
+                    variation_counts[depth] += 1;
+                    if !indented {
+                        line.push(self.describe_marker(&format!(
+                            "{} {} for {}'s move {}:",
+                            capitalize(&describe_variation(depth + 1)),
+                            variation_counts[depth],
+                            if ply % 2 == 1 { "White" } else { "Black" },
+                            ply.div_ceil(2)
+                        )));
+                        if !paragraph {
+                            flush_line(&mut lines, &mut line, depth, indented);
+                        }
+                    }
+                    depth += 1;
+                    variation_counts.push(0);
+                    back_to_line = false;
+                    after_white_move = false;
+                }
+                SolutionItem::VariationEnd => {
+                    if !paragraph || indented {
+                        flush_line(&mut lines, &mut line, depth, indented);
+                    }
+                    if !indented {
+                        line.push(
+                            self.describe_marker(&format!("End of {}.", describe_variation(depth))),
+                        );
+                        if !paragraph {
+                            flush_line(&mut lines, &mut line, depth, indented);
+                        }
+                    }
+                    depth = depth.saturating_sub(1);
+                    variation_counts.truncate(depth + 1);
+                    back_to_line = true;
+                    after_white_move = false;
+                }
+            }
+        }
+        flush_line(&mut lines, &mut line, depth, indented);
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    // Describes the words setting a side line apart, E.G. "End of variation.", as a sentence of its own in SSML.
+    fn describe_marker(&self, marker: &str) -> String {
+        if self.ssml {
+            format!("<s>{}</s>", ssml::ssml_text(marker))
+        } else {
+            marker.to_string()
+        }
+    }
+
+    // Describes a move number, E.G. "3." for White and "3..." for Black.
+    fn describe_move_number(&self, move_count: usize, black: bool) -> String {
+        if self.ssml {
+            // Verbose moves already start with the side, E.G. "Black knight moves from Gustav8 to Felix6".
+            ssml::ssml_move_number(move_count, black && self.verbosity != Verbosity::Verbose)
+        } else if black {
+            format!("{}...", move_count)
+        } else {
+            format!("{}.", move_count)
+        }
+    }
+}
+
+fn flush_line(lines: &mut Vec<String>, line: &mut Vec<String>, depth: usize, indented: bool) {
+    if line.is_empty() {
+        return;
+    }
+    let indentation = if indented {
+        "  ".repeat(depth)
+    } else {
+        String::new()
+    };
+    lines.push(format!("{}{}", indentation, line.join(" ")));
+    line.clear();
+}
+
+// Names a side line by how deeply it is nested, E.G. "variation" or "sub-variation".
+fn describe_variation(depth: usize) -> String {
+    match depth {
+        1 => String::from("variation"),
+        2 => String::from("sub-variation"),
+        _ => format!("level {} sub-variation", depth),
+    }
+}
+
+// Names the line a side line returns to, E.G. "main line" or "variation".
+fn describe_line(depth: usize) -> String {
+    match depth {
+        0 => String::from("main line"),
+        _ => describe_variation(depth),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
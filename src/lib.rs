@@ -14,24 +14,97 @@
 //     See the License for the specific language governing permissions and
 //     limitations under the License.
 
+mod attacks;
+mod audio;
+mod braille;
+mod comments;
+mod engine;
+mod epd;
+mod epub;
+mod features;
+mod headers;
+mod layout;
+mod lichess;
+mod mate;
+mod material;
+mod moves;
+mod pgn_export;
+mod ssml;
 #[cfg(test)]
 mod tests;
+mod verbosity;
+mod verify;
+mod zip;
 
+pub use audio::AudioExporter;
+pub use braille::BrailleFormat;
+pub use engine::{Analysis, Engine, EngineScore};
+pub use epub::EpubExporter;
+use layout::SolutionItem;
+pub use layout::{SolutionLayout, VariationStyle};
+pub use lichess::LichessFilter;
+pub use pgn_export::PgnAnnotator;
 use pgn_reader::{CastlingSide, Role};
 use pgn_reader::{Nag, Outcome, RawComment, RawHeader, San, SanPlus, Skip, Visitor};
-use tanton::board::{piece_locations, Board};
-use tanton::core::sq::SQ;
-use tanton::core::Player;
-use std;
+pub use ssml::join_ssml_documents;
 use std::collections::HashMap;
 use std::fmt::Write;
 use std::str;
+use tanton::board::{piece_locations, Board};
+use tanton::core::sq::SQ;
+use tanton::core::Player;
+use verbosity::Heading;
+pub use verbosity::Verbosity;
+
+// When moves say which square the piece comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveOrigin {
+    // Only when another piece of the same type could reach the same square, E.G. "Knight from Gustav1 to Eva2".
+    WhenHelpful,
+    // For every move, E.G. "Knight from Gustav1 to Felix3" or "Pawn from Eva2 to Eva4".
+    Always,
+}
+
+// An exercise as converted, with what exporters need to lay it out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvertedExercise {
+    // The number of the exercise in its file, E.G. 3 for "Exercise 3:". Games without exercise are not counted.
+    pub number: usize,
+    pub description: String,
+    // The lines of |description| that head a section, E.G. "White:" or "Solution:", counted from 0. Empty for SSML
+    // and braille, whose lines are laid out differently.
+    pub heading_lines: Vec<usize>,
+    // The line of |description| where the solution starts, E.G. the line of "Solution:", counted from 0. None if the
+    // exercise has no solution.
+    pub solution_line: Option<usize>,
+}
+
+// How the pieces of each side are listed in the position description.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceListStyle {
+    // One piece per line, E.G. "Rook Ana1".
+    Lines,
+    // One line per side, grouping pieces of the same type, E.G. "Pawns: Ana2, Bela2; Rooks: Ana1, Hector1".
+    Compact,
+    // Like |Compact|, but pawns on their starting rank are summarized, E.G. "Pawns on Ana2 through Hector2 except Eva2".
+    Summary,
+}
+
+// A side line being parsed.
+struct OpenVariation {
+    // Where the side line starts in |moves|.
+    start: usize,
+    // The ply count of the line it branches from, before its first move.
+    ply_count: usize,
+    // Whether the side line, or one nested in it, has a comment.
+    has_comment: bool,
+}
 
 pub struct PositionConverter {
     // The FEN of the position coming from the pgn header.
     starting_fen: String,
-    // Holds the pgn moves, converted to an accessible-friendly version.
-    moves: String,
+    // Holds the pgn moves, converted to an accessible-friendly version, with the comments and side lines around them.
+    moves: Vec<SolutionItem>,
     // Holds the final description of the game, containing initial position + |moves|.
     final_description: String,
     // Number of  the exercise coming from the pgn. A pgn file with 5 exercises would have 5 exercises for example.
@@ -41,22 +114,126 @@ pub struct PositionConverter {
     // 1. e4 (1. d4 Nf6) e5 *
     // The stack will have one value keeping track of the ply count of the main line. Once the first variation starts with 1. d4, another value is stacked and the ply count continues from there. Once the variation finishes, the ply count returns to the value of the previous line.
     ply_counts: Vec<usize>,
+    // Whether the last game converted by |end_game| was an exercise, rather than a game without starting position.
+    ended_with_exercise: bool,
+    // The heading lines of the description being written, see |ConvertedExercise|.
+    heading_lines: Vec<usize>,
+    // The solution line of the description being written, see |ConvertedExercise|.
+    solution_line: Option<usize>,
     // Whether to include side lines.
     with_side_lines: bool,
+    // Whether the side line being parsed is skipped. pgn-reader still reports its end.
+    skipping_variation: bool,
+    // The side lines being parsed, from the outermost one.
+    open_variations: Vec<OpenVariation>,
+    // If set, side lines nested deeper than this many levels are left out, E.G. 1 keeps side lines of the main line only.
+    max_variation_depth: Option<usize>,
+    // If set, moves of a side line past this many plies are left out, E.G. 2 keeps "1... Eva5 2. Knight Felix3".
+    max_variation_moves: Option<usize>,
+    // Whether to leave out side lines without comments, which often are engine output rather than explanations.
+    only_commented_variations: bool,
     // Whether to include pgn comments into the converted positions.
     with_comments: bool,
+    // How pieces are listed in the position description.
+    piece_list_style: PieceListStyle,
+    // Whether to add a material and imbalance summary before listing the pieces.
+    with_material: bool,
+    // Whether to add a section with key positional features after listing the pieces.
+    with_features: bool,
+    // Whether to annotate listed pieces with their square colour, diagonals and nearby corner.
+    with_square_hints: bool,
+    // Whether to add a section listing the squares each piece attacks and what defends it.
+    with_attacks: bool,
+    // Whether to list every legal move of the side to move.
+    with_legal_moves: bool,
+    // If set, the engine analyses every exercise position.
+    engine: Option<Engine>,
+    // The main line of the exercise being parsed, as found in the pgn.
+    main_line: Vec<San>,
+    // Whether to check the solutions of exercises instead of describing them.
+    verify: bool,
+    // How many moves deep the built-in mate search looks.
+    mate_search_depth: usize,
+    // Whether to search for a mate in exercises that come without solution.
+    with_computed_solutions: bool,
+    // The pgn headers of the exercise being parsed, in the order they appear.
+    headers: Vec<(String, String)>,
+    // Headers to read out in the exercise preamble, besides the known puzzle headers such as Goal or Themes.
+    extra_headers: Vec<String>,
+    // The title of the exercise being parsed, E.G. from the "id" opcode of EPD files. Pgn headers are not used, since
+    // a "Title" header usually is the title of a player.
+    exercise_title: Option<String>,
+    // Moves in SAN that look tempting but fail, E.G. from the "am" opcode of EPD files.
+    avoid_moves: Vec<String>,
+    // Whether to render descriptions as SSML for text-to-speech engines instead of plain text.
+    ssml: bool,
+    // If set, descriptions use the terse braille rendering, written out in this format.
+    braille: Option<BrailleFormat>,
+    // Number of cells of the braille display, E.G. 20 or 40. Longer lines are wrapped.
+    braille_line_width: usize,
+    // How much is said about pieces, moves and sections.
+    verbosity: Verbosity,
+    // The positions of the lines being parsed, one entry per value of |ply_counts|: the position before the last move,
+    // where a side line starts from, and the current position. Positions are None once a move cannot be played.
+    line_boards: Vec<(Option<Board>, Option<Board>)>,
+    // When moves say which square the piece comes from.
+    move_origin: MoveOrigin,
+    // How the moves of solutions are laid out.
+    solution_layout: SolutionLayout,
+    // How side lines are set apart from the line they branch from.
+    variation_style: VariationStyle,
+    // Whether each ply of a solution starts with "White:" or "Black:".
+    with_side_prefixes: bool,
+}
+
+impl Default for PositionConverter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PositionConverter {
     pub fn new() -> PositionConverter {
         PositionConverter {
             starting_fen: String::from(""),
-            moves: String::from(""),
+            moves: vec![],
             final_description: String::from(""),
             exercise_number: 0,
             ply_counts: vec![0],
+            ended_with_exercise: false,
+            heading_lines: vec![],
+            solution_line: None,
             with_side_lines: false,
+            skipping_variation: false,
+            open_variations: vec![],
+            max_variation_depth: None,
+            max_variation_moves: None,
+            only_commented_variations: false,
             with_comments: false,
+            piece_list_style: PieceListStyle::Lines,
+            with_material: false,
+            with_features: false,
+            with_square_hints: false,
+            with_attacks: false,
+            with_legal_moves: false,
+            engine: None,
+            main_line: vec![],
+            verify: false,
+            mate_search_depth: 3,
+            with_computed_solutions: false,
+            headers: vec![],
+            extra_headers: vec![],
+            exercise_title: None,
+            avoid_moves: vec![],
+            ssml: false,
+            braille: None,
+            braille_line_width: 40,
+            verbosity: Verbosity::Normal,
+            line_boards: vec![(None, None)],
+            move_origin: MoveOrigin::WhenHelpful,
+            solution_layout: SolutionLayout::PairPerLine,
+            variation_style: VariationStyle::Spoken,
+            with_side_prefixes: false,
         }
     }
 
@@ -67,13 +244,133 @@ impl PositionConverter {
         pc
     }
 
+    pub fn set_piece_list_style(&mut self, piece_list_style: PieceListStyle) {
+        self.piece_list_style = piece_list_style;
+    }
+
+    pub fn set_with_material(&mut self, with_material: bool) {
+        self.with_material = with_material;
+    }
+
+    pub fn set_with_features(&mut self, with_features: bool) {
+        self.with_features = with_features;
+    }
+
+    pub fn set_with_square_hints(&mut self, with_square_hints: bool) {
+        self.with_square_hints = with_square_hints;
+    }
+
+    pub fn set_with_attacks(&mut self, with_attacks: bool) {
+        self.with_attacks = with_attacks;
+    }
+
+    pub fn set_with_legal_moves(&mut self, with_legal_moves: bool) {
+        self.with_legal_moves = with_legal_moves;
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = Some(engine);
+    }
+
+    // In verify mode, each exercise yields a line reporting suspicious solutions instead of its description.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    pub fn set_mate_search_depth(&mut self, mate_search_depth: usize) {
+        self.mate_search_depth = mate_search_depth;
+    }
+
+    pub fn set_with_computed_solutions(&mut self, with_computed_solutions: bool) {
+        self.with_computed_solutions = with_computed_solutions;
+    }
+
+    pub fn set_extra_headers(&mut self, extra_headers: Vec<String>) {
+        self.extra_headers = extra_headers;
+    }
+
+    pub fn set_ssml(&mut self, ssml: bool) {
+        self.ssml = ssml;
+    }
+
+    pub fn set_braille(&mut self, braille: Option<BrailleFormat>) {
+        self.braille = braille;
+    }
+
+    pub fn set_braille_line_width(&mut self, braille_line_width: usize) {
+        self.braille_line_width = braille_line_width;
+    }
+
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn set_move_origin(&mut self, move_origin: MoveOrigin) {
+        self.move_origin = move_origin;
+    }
+
+    pub fn set_solution_layout(&mut self, solution_layout: SolutionLayout) {
+        self.solution_layout = solution_layout;
+    }
+
+    pub fn set_variation_style(&mut self, variation_style: VariationStyle) {
+        self.variation_style = variation_style;
+    }
+
+    pub fn set_with_side_prefixes(&mut self, with_side_prefixes: bool) {
+        self.with_side_prefixes = with_side_prefixes;
+    }
+
+    pub fn set_max_variation_depth(&mut self, max_variation_depth: Option<usize>) {
+        self.max_variation_depth = max_variation_depth;
+    }
+
+    pub fn set_max_variation_moves(&mut self, max_variation_moves: Option<usize>) {
+        self.max_variation_moves = max_variation_moves;
+    }
+
+    pub fn set_only_commented_variations(&mut self, only_commented_variations: bool) {
+        self.only_commented_variations = only_commented_variations;
+    }
+
+    // Returns the exercise converted by the last call to |end_game|, which described it as |description|, or None if
+    // that game had no exercise.
+    pub fn get_converted_exercise(&self, description: String) -> Option<ConvertedExercise> {
+        if !self.ended_with_exercise {
+            return None;
+        }
+        Some(ConvertedExercise {
+            number: self.exercise_number,
+            description,
+            heading_lines: self.heading_lines.clone(),
+            solution_line: self.solution_line,
+        })
+    }
+
+    // Writes |heading| as a line of |description| and remembers that line as a heading.
+    pub(crate) fn write_heading(&mut self, description: &mut String, heading: &str) {
+        self.heading_lines.push(description.matches('\n').count());
+        writeln!(description, "{}", heading).unwrap();
+    }
+
     fn describe_board(
         &mut self,
         board: Board,
         board_pieces: piece_locations::PieceLocations,
     ) -> String {
         let mut description = String::new();
-        write!(&mut description, "Exercise {}:\n", self.exercise_number).unwrap();
+        match &self.exercise_title {
+            Some(title) => writeln!(
+                &mut description,
+                "Exercise {}: {}",
+                self.exercise_number, title
+            )
+            .unwrap(),
+            None => writeln!(&mut description, "Exercise {}:", self.exercise_number).unwrap(),
+        }
+        if let Some(puzzle_headers) = self.describe_puzzle_headers() {
+            writeln!(&mut description, "{}", puzzle_headers).unwrap();
+        }
         // Key = the FEN character that represents the piece.
         // Value = the squares containing that piece.
         let mut piece_to_squares: HashMap<char, Vec<SQ>> = HashMap::new();
@@ -84,24 +381,113 @@ impl PositionConverter {
         }
         let white_pieces = "PNBRQK";
         let black_pieces = "pnbrqk";
-        match board.turn() {
-            Player::White => {
-                write!(&mut description, "White to move:\n").unwrap();
-                write!(&mut description, "White:\n").unwrap();
-                self.describe_pieces(&mut description, &white_pieces, &piece_to_squares);
-                write!(&mut description, "Black:\n").unwrap();
-                self.describe_pieces(&mut description, &black_pieces, &piece_to_squares);
+        // The side to move is described first.
+        let sides = match board.turn() {
+            Player::White => [("White", white_pieces), ("Black", black_pieces)],
+            Player::Black => [("Black", black_pieces), ("White", white_pieces)],
+        };
+        writeln!(
+            &mut description,
+            "{}",
+            self.describe_heading(Heading::SideToMove(sides[0].0))
+        )
+        .unwrap();
+        if self.with_material {
+            self.describe_material(&mut description, &piece_to_squares);
+        }
+        let pieces_start = description.len();
+        for (side, pieces) in sides.iter() {
+            self.write_heading(
+                &mut description,
+                &self.describe_heading(Heading::Pieces(side)),
+            );
+            if self.braille.is_some() {
+                self.describe_braille_pieces(&mut description, pieces, &piece_to_squares);
+            } else {
+                self.describe_pieces(&mut description, pieces, &piece_to_squares);
             }
-            Player::Black => {
-                write!(&mut description, "Black to move:\n").unwrap();
-                write!(&mut description, "Black:\n").unwrap();
-                self.describe_pieces(&mut description, &black_pieces, &piece_to_squares);
-                write!(&mut description, "White:\n").unwrap();
-                self.describe_pieces(&mut description, &white_pieces, &piece_to_squares);
+        }
+        let pieces_end = description.len();
+        if self.with_features {
+            self.describe_features(&mut description, &board);
+        }
+        if self.with_attacks {
+            self.describe_attacks(&mut description, &board);
+        }
+        if self.with_legal_moves {
+            self.write_heading(
+                &mut description,
+                &self.describe_heading(Heading::LegalMoves),
+            );
+            description.push_str(&self.describe_legal_moves_of_board(&board));
+        }
+        if let Some(engine) = self.engine.as_mut() {
+            let analysis = engine.analyse(&board.fen());
+            match analysis {
+                Ok(analysis) => writeln!(
+                    &mut description,
+                    "Engine: {}",
+                    self.describe_analysis(&board, &analysis)
+                )
+                .unwrap(),
+                Err(error) => {
+                    writeln!(&mut description, "Engine: analysis failed, {}", error).unwrap()
+                }
             }
-        };
+        }
+        let solution_start = description.len();
         if !self.moves.is_empty() {
-            write!(&mut description, "Solution:\n{}", self.moves).unwrap();
+            self.solution_line = Some(description.matches('\n').count());
+            self.write_heading(&mut description, &self.describe_heading(Heading::Solution));
+            description.push_str(&self.render_solution(&self.moves));
+        } else if self.with_computed_solutions {
+            if let Some(line) = mate::find_mate(&board, self.mate_search_depth) {
+                let heading =
+                    self.describe_heading(Heading::ComputedSolution(line.len().div_ceil(2)));
+                self.solution_line = Some(description.matches('\n').count());
+                self.write_heading(&mut description, &heading);
+                description.push_str(&self.describe_computed_solution(&board, &line));
+            }
+        }
+        // Braille wraps long lines and SSML is laid out in paragraphs, so the heading lines no longer match.
+        if self.ssml || self.braille.is_some() {
+            self.heading_lines.clear();
+        }
+        if self.ssml {
+            // Moves of the solution are already SSML, written by |san|.
+            let solution = if self.moves.is_empty() {
+                ssml::ssml_lines(&description[solution_start..])
+            } else {
+                format!(
+                    "<s>{}</s>\n{}",
+                    self.describe_heading(Heading::Solution),
+                    self.render_solution(&self.moves)
+                )
+            };
+            let position = format!(
+                "<p>{}</p>\n{}\n<p>{}</p>\n",
+                ssml::ssml_lines(&description[..pieces_start]),
+                ssml::ssml_piece_list(&description[pieces_start..pieces_end]),
+                ssml::ssml_lines(&description[pieces_end..solution_start]),
+            )
+            .replace("<p></p>\n", "");
+            // The solution paragraph follows the position, after the line opening the document.
+            if self.solution_line.is_some() {
+                self.solution_line = Some(1 + position.matches('\n').count());
+            }
+            return ssml::ssml_document(&format!("{}<p>{}</p>", position, solution))
+                .replace("<p></p>\n", "");
+        }
+        if self.braille.is_some() {
+            // Long lines are wrapped, so the solution starts after the wrapped lines of the position.
+            if self.solution_line.is_some() {
+                self.solution_line = Some(
+                    self.format_braille(&description[..solution_start])
+                        .lines()
+                        .count(),
+                );
+            }
+            return self.format_braille(&description);
         }
         description
     }
@@ -112,6 +498,10 @@ impl PositionConverter {
         pieces: &str,
         piece_to_squares: &HashMap<char, Vec<SQ>>,
     ) {
+        if self.piece_list_style != PieceListStyle::Lines {
+            self.describe_pieces_compact(description, pieces, piece_to_squares);
+            return;
+        }
         for piece_with_color in pieces.chars() {
             if !piece_to_squares.contains_key(&piece_with_color) {
                 continue;
@@ -121,13 +511,126 @@ impl PositionConverter {
             let piece = piece_with_color.to_lowercase().to_string();
             let name: &str = self.get_piece_name(&piece[..]);
             for square in piece_to_squares[&piece_with_color].iter() {
-                write!(description, "{} {}\n", name, self.describe_square(&square)).unwrap();
+                let square_description = self.describe_square_with_hints(square, &piece);
+                if self.verbosity == Verbosity::Verbose {
+                    writeln!(
+                        description,
+                        "{}",
+                        self.describe_verbose_piece(piece_with_color, &square_description)
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(description, "{} {}", name, square_description).unwrap();
+                }
             }
         }
     }
 
+    // Writes all pieces of one side in a single line, grouped by piece type.
+    fn describe_pieces_compact(
+        &self,
+        description: &mut String,
+        pieces: &str,
+        piece_to_squares: &HashMap<char, Vec<SQ>>,
+    ) {
+        let mut groups: Vec<String> = vec![];
+        for piece_with_color in pieces.chars() {
+            let squares = match piece_to_squares.get(&piece_with_color) {
+                Some(squares) => squares,
+                None => continue,
+            };
+            let piece = piece_with_color.to_lowercase().to_string();
+            if piece == "p" && self.piece_list_style == PieceListStyle::Summary {
+                // White pawns start on the second rank, black pawns on the seventh. Ranks are zero based here.
+                let starting_rank = if piece_with_color.is_uppercase() {
+                    1
+                } else {
+                    6
+                };
+                if let Some(summary) = self.summarize_pawns(squares, starting_rank) {
+                    groups.push(summary);
+                    continue;
+                }
+            }
+            let name = if squares.len() == 1 {
+                self.get_piece_name(&piece[..])
+            } else {
+                self.get_piece_plural_name(&piece[..])
+            };
+            let square_names: Vec<String> = squares
+                .iter()
+                .map(|square| self.describe_square_with_hints(square, &piece))
+                .collect();
+            groups.push(format!("{}: {}", name, square_names.join(", ")));
+        }
+        if !groups.is_empty() {
+            writeln!(description, "{}", groups.join("; ")).unwrap();
+        }
+    }
+
+    // Summarizes the pawns of one side when most of them are still on |starting_rank|, E.G. "Pawns on Ana2 through Hector2 except Eva2, and Eva4".
+    // Returns None when a summary would not be shorter than listing the pawns.
+    fn summarize_pawns(&self, squares: &[SQ], starting_rank: u8) -> Option<String> {
+        let (home, advanced): (Vec<&SQ>, Vec<&SQ>) = squares
+            .iter()
+            .partition(|square| square.rank_idx_of_sq() == starting_rank);
+        if home.len() < 4 {
+            return None;
+        }
+        let rank = starting_rank + 1;
+        let mut summary = String::new();
+        if home.len() == 8 {
+            write!(
+                summary,
+                "All {} pawns on their starting squares",
+                self.get_count_name(home.len())
+            )
+            .unwrap();
+        } else {
+            let files: Vec<u8> = home.iter().map(|square| square.file_idx_of_sq()).collect();
+            let first = *files.iter().min().unwrap();
+            let last = *files.iter().max().unwrap();
+            let missing: Vec<String> = (first..=last)
+                .filter(|file| !files.contains(file))
+                .map(|file| format!("{}{}", self.get_file_name(file), rank))
+                .collect();
+            if missing.len() > 2 {
+                return None;
+            }
+            write!(
+                summary,
+                "Pawns on {}{} through {}{}",
+                self.get_file_name(first),
+                rank,
+                self.get_file_name(last),
+                rank
+            )
+            .unwrap();
+            if !missing.is_empty() {
+                write!(summary, " except {}", missing.join(" and ")).unwrap();
+            }
+        }
+        if !advanced.is_empty() {
+            let square_names: Vec<String> = advanced
+                .iter()
+                .map(|square| self.describe_square(square))
+                .collect();
+            write!(summary, ", and {}", square_names.join(", ")).unwrap();
+        }
+        Some(summary)
+    }
+
     fn get_piece_name(&self, piece: &str) -> &'static str {
-        let piece_name = match piece {
+        if self.braille.is_some() {
+            return self.get_braille_piece_name(piece);
+        }
+        self.get_full_piece_name(piece)
+    }
+
+    // Names pieces in words even in braille, for prose that the letters would garble, E.G. "White has rook and two
+    // pawns" rather than "White has r and two p".
+    fn get_full_piece_name(&self, piece: &str) -> &'static str {
+        match piece {
             "p" => "Pawn",
             "n" => "Knight",
             "b" => "Bishop",
@@ -135,12 +638,58 @@ impl PositionConverter {
             "q" => "Queen",
             "k" => "King",
             _ => "None",
+        }
+    }
+
+    fn get_piece_plural_name(&self, piece: &str) -> &'static str {
+        if self.braille.is_some() {
+            return self.get_braille_piece_name(piece);
+        }
+        self.get_full_piece_plural_name(piece)
+    }
+
+    fn get_full_piece_plural_name(&self, piece: &str) -> &'static str {
+        match piece {
+            "p" => "Pawns",
+            "n" => "Knights",
+            "b" => "Bishops",
+            "r" => "Rooks",
+            "q" => "Queens",
+            "k" => "Kings",
+            _ => "None",
+        }
+    }
+
+    fn get_count_name(&self, count: usize) -> String {
+        let count_name = match count {
+            0 => "no",
+            1 => "one",
+            2 => "two",
+            3 => "three",
+            4 => "four",
+            5 => "five",
+            6 => "six",
+            7 => "seven",
+            8 => "eight",
+            9 => "nine",
+            10 => "ten",
+            _ => return count.to_string(),
         };
-        piece_name
+        count_name.to_string()
+    }
+
+    fn get_player_name(&self, player: Player) -> &'static str {
+        match player {
+            Player::White => "White",
+            Player::Black => "Black",
+        }
     }
 
     fn get_file_name(&self, file_number: u8) -> &'static str {
-        let file_name = match file_number {
+        if self.braille.is_some() || self.verbosity == Verbosity::Terse {
+            return self.get_braille_file_name(file_number);
+        }
+        match file_number {
             0 => "Ana",
             1 => "Bela",
             2 => "Caesar",
@@ -150,8 +699,7 @@ impl PositionConverter {
             6 => "Gustav",
             7 => "Hector",
             _ => "unknown",
-        };
-        file_name
+        }
     }
 
     fn describe_square(&self, square: &SQ) -> String {
@@ -163,46 +711,86 @@ impl PositionConverter {
         square_description
     }
 
-    fn get_ply_count(&self) -> usize {
-        let ply_count = *self.ply_counts.last().unwrap();
-        ply_count
-    }
-
-    fn get_move_count(&self) -> usize {
-        // Each two ply = one move in chess.
-        let move_count: usize = self.get_ply_count() / 2 + 1;
-        move_count
-    }
-}
-
-impl Visitor for PositionConverter {
-    type Result = String;
-    fn begin_game(&mut self) {}
-
-    fn begin_headers(&mut self) {}
-
-    fn header(&mut self, _key: &[u8], _value: RawHeader) {
-        let key_str = match str::from_utf8(_key) {
-            Ok(v) => v,
-            Err(e) => panic!("Invalid UTF-8 sequence in fen header key: {}", e),
+    // Describes the square of a listed |piece|, adding visualisation hints when enabled, E.G.
+    // "Caesar1, dark square, diagonals Ana3 to Caesar1 and Caesar1 to Hector6".
+    fn describe_square_with_hints(&self, square: &SQ, piece: &str) -> String {
+        let mut square_description = self.describe_square(square);
+        if !self.with_square_hints {
+            return square_description;
+        }
+        // a1 is a dark square, so are all squares whose file and rank indexes add up to an even number.
+        // Note that tanton's SQ::on_dark_square() has this the other way around, so it is not used here.
+        let color = if (square.file_idx_of_sq() + square.rank_idx_of_sq()).is_multiple_of(2) {
+            "dark"
+        } else {
+            "light"
         };
-        if key_str == "FEN" && self.starting_fen.is_empty() {
-            let fen = _value.decode_utf8().unwrap();
-            self.starting_fen.push_str(&fen);
+        write!(&mut square_description, ", {} square", color).unwrap();
+        let file = square.file_idx_of_sq();
+        let rank = square.rank_idx_of_sq();
+        if piece == "b" || piece == "q" {
+            // Each diagonal is described from its leftmost to its rightmost square.
+            let rising = file.min(rank);
+            let rising_start = (file - rising, rank - rising);
+            let rising_end = (
+                file + (7 - file).min(7 - rank),
+                rank + (7 - file).min(7 - rank),
+            );
+            let falling = file.min(7 - rank);
+            let falling_start = (file - falling, rank + falling);
+            let falling_end = (file + (7 - file).min(rank), rank - (7 - file).min(rank));
+            let mut diagonals: Vec<String> = vec![];
+            for (start, end) in [(rising_start, rising_end), (falling_start, falling_end)].iter() {
+                // A corner square is its own diagonal, which is not worth mentioning.
+                if start == end {
+                    continue;
+                }
+                let long = if end.0 - start.0 == 7 { "long " } else { "" };
+                diagonals.push(format!(
+                    "{}diagonal {}{} to {}{}",
+                    long,
+                    self.get_file_name(start.0),
+                    start.1 + 1,
+                    self.get_file_name(end.0),
+                    end.1 + 1
+                ));
+            }
+            write!(&mut square_description, ", {}", diagonals.join(" and ")).unwrap();
+        }
+        for corner in [(0, 0), (7, 0), (0, 7), (7, 7)].iter() {
+            let distance = (file as i8 - corner.0 as i8)
+                .abs()
+                .max((rank as i8 - corner.1 as i8).abs());
+            if distance > 1 {
+                continue;
+            }
+            let position = if distance == 0 { "in" } else { "near" };
+            write!(
+                &mut square_description,
+                ", {} the {}{} corner",
+                position,
+                self.get_file_name(corner.0),
+                corner.1 + 1
+            )
+            .unwrap();
         }
+        square_description
     }
 
-    fn end_headers(&mut self) -> Skip {
-        Skip(false)
+    // Converts a move in SAN to its accessible-friendly version, E.G. "Nxe5" becomes "Knight takes Eva5".
+    fn describe_san(&self, san: &San) -> String {
+        self.describe_san_from(san, None)
     }
 
-    fn san(&mut self, _san_plus: SanPlus) {
-        *self.ply_counts.last_mut().unwrap() += 1;
-        // Writes the move number every two moves, E.G. 1. e4 e5 2. Nf3 Nc6.
-        if self.get_ply_count() % 2 == 1 {
-            write!(self.moves, "{}. ", self.get_move_count()).unwrap();
+    // Like |describe_san|, but says where the piece comes from if |origin| is known and it is configured, E.G.
+    // "Knight from Gustav1 takes Eva5". Otherwise SAN disambiguation is read out, E.G. "Knight from the Gustav file
+    // takes Eva5" or "Rook from rank 5 to Gustav7".
+    fn describe_san_from(&self, san: &San, origin: Option<SQ>) -> String {
+        if self.braille.is_some() {
+            return self.describe_braille_san(san);
         }
-        match _san_plus.san {
+        let mut move_description = String::new();
+        match *san {
             San::Normal {
                 role,      // The piece.
                 file,      // The file to disambiguate, E.G. Rfe1.
@@ -211,25 +799,48 @@ impl Visitor for PositionConverter {
                 to,        // Destination square.
                 promotion, // Contains the promotion piece if promoting.
             } => {
-                if role != Role::Pawn {
+                let origin = match origin {
+                    Some(origin) if self.move_origin == MoveOrigin::Always => Some(origin),
+                    // Pawn captures already name the file the pawn comes from.
+                    Some(origin) if role != Role::Pawn && (file.is_some() || rank.is_some()) => {
+                        Some(origin)
+                    }
+                    _ => None,
+                };
+                if role != Role::Pawn || origin.is_some() {
                     write!(
-                        self.moves,
+                        move_description,
                         "{} ",
                         self.get_piece_name(&role.char().to_string())
                     )
                     .unwrap();
                 }
-                if let Some(file) = file {
-                    write!(self.moves, "{} ", self.get_file_name(file as u8)).unwrap();
+                // Pawn captures name the file the pawn comes from, E.G. "Eva takes David5".
+                if let (Some(file), None, Role::Pawn) = (file, origin, role) {
+                    write!(move_description, "{} ", self.get_file_name(file as u8)).unwrap();
                 }
-                if let Some(rank) = rank {
-                    write!(self.moves, "{} ", rank.char()).unwrap();
+                let from = match (origin, file, rank) {
+                    (Some(origin), _, _) => Some(self.describe_square(&origin)),
+                    _ if role == Role::Pawn => None,
+                    (None, Some(file), Some(rank)) => {
+                        Some(format!("{}{}", self.get_file_name(file as u8), rank.char()))
+                    }
+                    (None, Some(file), None) => {
+                        Some(format!("the {} file", self.get_file_name(file as u8)))
+                    }
+                    (None, None, Some(rank)) => Some(format!("rank {}", rank.char())),
+                    (None, None, None) => None,
+                };
+                if let Some(from) = &from {
+                    write!(move_description, "from {} ", from).unwrap();
                 }
                 if capture {
-                    write!(self.moves, "takes ").unwrap();
+                    write!(move_description, "takes ").unwrap();
+                } else if from.is_some() {
+                    write!(move_description, "to ").unwrap();
                 }
                 write!(
-                    self.moves,
+                    move_description,
                     "{}{}",
                     self.get_file_name(to.file() as u8),
                     to.rank().char()
@@ -237,96 +848,245 @@ impl Visitor for PositionConverter {
                 .unwrap();
                 if let Some(promotion) = promotion {
                     write!(
-                        self.moves,
+                        move_description,
                         " promotes to {}",
                         self.get_piece_name(&promotion.char().to_string())
                     )
                     .unwrap();
                 }
             }
-            San::Castle(CastlingSide::KingSide) => write!(self.moves, "Short Castling").unwrap(),
-            San::Castle(CastlingSide::QueenSide) => write!(self.moves, "Long Castling").unwrap(),
-            _ => write!(self.moves, "--").unwrap(),
+            San::Castle(CastlingSide::KingSide) => {
+                write!(move_description, "Short Castling").unwrap()
+            }
+            San::Castle(CastlingSide::QueenSide) => {
+                write!(move_description, "Long Castling").unwrap()
+            }
+            _ => write!(move_description, "--").unwrap(),
+        }
+        move_description
+    }
+
+    // Describes |san| played on |board|, which tells where the piece comes from, E.G. "Knight from Gustav1 to Felix3".
+    // |board| is None when the position is unknown, E.G. after an illegal move.
+    pub(crate) fn describe_move_on_board(
+        &self,
+        board: Option<&Board>,
+        san: &San,
+        white: bool,
+    ) -> String {
+        if self.verbosity == Verbosity::Verbose {
+            return self.describe_verbose_move(board, san, white);
         }
-        // Keep two moves per line.
-        if self.get_ply_count() % 2 == 0 {
-            write!(self.moves, "\n").unwrap();
+        let origin = board
+            .and_then(|board| moves::find_san_move(board, san).map(|bit_move| bit_move.get_src()));
+        self.describe_san_from(san, origin)
+    }
+
+    // Plays |san| on the current position of the line being parsed, keeping the position before it for side lines.
+    fn play_on_line_board(&mut self, san: &San) {
+        let line_board = self.line_boards.last_mut().unwrap();
+        let next_board = moves::play_san(line_board.1.as_ref(), san);
+        line_board.0 = std::mem::replace(&mut line_board.1, next_board);
+    }
+
+    // Whether the last move is past |max_variation_moves| in the side line being parsed.
+    fn is_past_variation_length(&self) -> bool {
+        match (self.open_variations.last(), self.max_variation_moves) {
+            (Some(variation), Some(max_variation_moves)) => {
+                self.get_ply_count() - variation.ply_count > max_variation_moves
+            }
+            _ => false,
+        }
+    }
+
+    fn get_ply_count(&self) -> usize {
+        let ply_count = *self.ply_counts.last().unwrap();
+        ply_count
+    }
+}
+
+// Joins |items| into a spoken list, E.G. "Eva5, Gustav5 and Hector4".
+pub(crate) fn join_with_and(items: &[String]) -> String {
+    match items.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+// Returns the ply of the first move of the position described by |fen|, based on its side to move and move number.
+pub(crate) fn get_starting_ply(fen: &str) -> usize {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let fullmoves: usize = fields
+        .get(5)
+        .and_then(|fullmoves| fullmoves.parse().ok())
+        .unwrap_or(1);
+    let black_to_move = fields.get(1) == Some(&"b");
+    fullmoves.max(1) * 2 - 2 + black_to_move as usize
+}
+
+impl Visitor for PositionConverter {
+    type Result = String;
+    fn begin_game(&mut self) {}
+
+    fn begin_headers(&mut self) {}
+
+    fn header(&mut self, _key: &[u8], _value: RawHeader) {
+        let key_str = match str::from_utf8(_key) {
+            Ok(v) => v,
+            Err(e) => panic!("Invalid UTF-8 sequence in fen header key: {}", e),
+        };
+        if key_str == "FEN" && self.starting_fen.is_empty() {
+            let fen = _value.decode_utf8().unwrap();
+            self.starting_fen.push_str(&fen);
+        }
+        self.headers
+            .push((key_str.to_string(), _value.decode_utf8_lossy().into_owned()));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let board = if self.starting_fen.is_empty() {
+            Some(Board::start_pos())
         } else {
-            write!(self.moves, " ").unwrap();
+            Board::from_fen(&self.starting_fen).ok()
+        };
+        // Move numbers follow the FEN, like in annotated pgn, E.G. "23... Rook Ana1" when Black moves first on move 23.
+        if !self.starting_fen.is_empty() {
+            self.ply_counts = vec![get_starting_ply(&self.starting_fen)];
+        }
+        self.line_boards = vec![(None, board)];
+        Skip(false)
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        *self.ply_counts.last_mut().unwrap() += 1;
+        if self.is_past_variation_length() {
+            return;
         }
+        if self.ply_counts.len() == 1 {
+            self.main_line.push(_san_plus.san.clone());
+        }
+        let move_description = self.describe_move_on_board(
+            self.line_boards.last().unwrap().1.as_ref(),
+            &_san_plus.san,
+            self.get_ply_count() % 2 == 1,
+        );
+        self.play_on_line_board(&_san_plus.san);
+        let move_description = if self.braille.is_some() {
+            // SAN keeps its check and mate suffix in braille, E.G. "Ra8#".
+            _san_plus.to_string()
+        } else if self.ssml {
+            ssml::ssml_move(&move_description, &_san_plus)
+        } else {
+            move_description
+        };
+        self.moves.push(SolutionItem::Move {
+            ply: self.get_ply_count(),
+            description: move_description,
+        });
     }
 
     fn nag(&mut self, _nag: Nag) {}
 
     fn comment(&mut self, _comment: RawComment) {
+        if self.is_past_variation_length() {
+            return;
+        }
+        // Older pgn files can be in Latin-1, E.G. "{Sch\xf6n}", so invalid bytes are replaced rather than fatal.
+        let comment_str = String::from_utf8_lossy(_comment.as_bytes());
+        let comment_description = self.describe_comment(&comment_str);
+        // Comments with only commands that are dropped, E.G. "[%emt 0:00:05]", are ignored.
+        if comment_description.is_empty() {
+            return;
+        }
+        for variation in self.open_variations.iter_mut() {
+            variation.has_comment = true;
+        }
         if self.with_comments {
-            let comment_str = str::from_utf8(_comment.as_bytes()).unwrap();
-            write!(self.moves, "\n{}\n", comment_str).unwrap();
+            self.moves.push(SolutionItem::Comment(comment_description));
         }
     }
 
     fn begin_variation(&mut self) -> Skip {
-        if !self.with_side_lines {
-            return Skip(true); // stay in the mainline
+        // |ply_counts| has one value for the main line and one for each side line being parsed.
+        if !self.with_side_lines
+            || self
+                .max_variation_depth
+                .is_some_and(|max_variation_depth| self.ply_counts.len() > max_variation_depth)
+            || self.is_past_variation_length()
+        {
+            self.skipping_variation = true;
+            return Skip(true); // stay in the current line
         }
         // Note that the ply count is reset by one since a side line in pgn undoes the last move and then starts.
-
-        self.ply_counts.push(self.get_ply_count() - 1);
-        // We write the move count here to support things of the form 1. e4 e5 (1... d5)
-        if self.get_ply_count() % 2 == 1 {
-            write!(self.moves, "({}... ", self.get_move_count()).unwrap();
-        } else {
-            write!(self.moves, "\n(").unwrap();
-        }
+        let ply = self.get_ply_count();
+        self.ply_counts.push(ply - 1);
+        self.open_variations.push(OpenVariation {
+            start: self.moves.len(),
+            ply_count: ply - 1,
+            has_comment: false,
+        });
+        let start_board = self.line_boards.last().unwrap().0.clone();
+        self.line_boards.push((None, start_board));
+        self.moves.push(SolutionItem::VariationStart { ply });
         Skip(false)
     }
 
     fn end_variation(&mut self) {
-        if self.get_ply_count() % 2 == 0 {
-            // Writes the \n after the ) of the end line.
-            unsafe {
-                let buffer = self.moves.as_mut_vec();
-                let index = buffer.len() - 1;
-                buffer[index] = ')' as u8;
-            }
-            write!(self.moves, "\n").unwrap();
+        if self.skipping_variation {
+            self.skipping_variation = false;
+            return;
+        }
+        let variation = self.open_variations.pop().unwrap();
+        if self.only_commented_variations && !variation.has_comment {
+            self.moves.truncate(variation.start);
         } else {
-            write!(self.moves, ")\n").unwrap();
+            self.moves.push(SolutionItem::VariationEnd);
         }
-
         self.ply_counts.pop();
+        self.line_boards.pop();
     }
 
     fn outcome(&mut self, _outcome: Option<Outcome>) {}
 
     fn end_game(&mut self) -> Self::Result {
-        // If there was an odd ply, this means that |moves| is missing a new line to end it.
-        if self.ply_counts.last().unwrap() % 2 == 1 {
-            unsafe {
-                let buffer = self.moves.as_mut_vec();
-                let index = buffer.len() - 1;
-                buffer[index] = '\n' as u8;
-            }
-        }
-
+        self.ended_with_exercise = !self.starting_fen.is_empty();
+        self.heading_lines.clear();
+        self.solution_line = None;
         if !self.starting_fen.is_empty() {
             let board = Board::from_fen(&self.starting_fen[..]).unwrap();
-            let board_pieces = board.get_piece_locations();
             self.exercise_number += 1;
-            self.final_description = self.describe_board(board, board_pieces);
-        } else if !self.moves.is_empty() {
+            if self.verify {
+                self.final_description = self.verify_exercise(&board);
+            } else {
+                let board_pieces = board.get_piece_locations();
+                self.final_description = self.describe_board(board, board_pieces);
+            }
+        } else if !self.moves.is_empty() && !self.verify {
             // There is no exercise, but there is a regular game.
-            let temp = std::mem::replace(&mut self.moves, String::new());
-            std::mem::replace(&mut self.final_description, temp);
+            self.final_description = self.render_solution(&self.moves);
+            if self.ssml {
+                self.final_description =
+                    ssml::ssml_document(&format!("<p>{}</p>", self.final_description));
+            } else if self.braille.is_some() {
+                self.final_description = self.format_braille(&self.final_description);
+            }
         }
         // Clears fields for next round.
         self.starting_fen.clear();
         self.moves.clear();
+        self.main_line.clear();
+        self.headers.clear();
+        self.exercise_title = None;
+        self.avoid_moves.clear();
         self.ply_counts = vec![0];
+        self.skipping_variation = false;
+        self.open_variations.clear();
+        self.line_boards = vec![(None, None)];
         // TODO: is there a way to return self.final_description directly from this mutable reference?
         // alternative 1:
         //self.final_description.clone()
         // Alternative 2:
-        std::mem::replace(&mut self.final_description, String::new())
+        std::mem::take(&mut self.final_description)
     }
 }
@@ -0,0 +1,136 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Reads the Lichess puzzle database, a CSV file with one puzzle per row:
+// PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+// Each row goes through the same Visitor as a pgn game, so it is described like any other exercise.
+
+use super::moves::{find_uci_move, san_plus_from_bit_move};
+use super::{ConvertedExercise, PositionConverter};
+use pgn_reader::{RawHeader, SanPlus, Visitor};
+use std::io;
+use tanton::board::Board;
+
+// Which puzzles of the database to convert.
+#[derive(Clone, Debug, Default)]
+pub struct LichessFilter {
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    // If not empty, only puzzles with at least one of these themes are converted, E.G. "fork".
+    pub themes: Vec<String>,
+}
+
+impl LichessFilter {
+    fn accepts(&self, rating: u32, themes: &str) -> bool {
+        if self
+            .min_rating
+            .is_some_and(|min_rating| rating < min_rating)
+            || self
+                .max_rating
+                .is_some_and(|max_rating| rating > max_rating)
+        {
+            return false;
+        }
+        self.themes.is_empty()
+            || themes
+                .split_whitespace()
+                .any(|theme| self.themes.iter().any(|wanted| wanted == theme))
+    }
+}
+
+impl PositionConverter {
+    // Rows that cannot be converted, E.G. with an illegal move, are reported on stderr and skipped, so one bad row of a
+    // large database does not stop the others.
+    pub fn convert_lichess_csv(
+        &mut self,
+        csv: &str,
+        filter: &LichessFilter,
+    ) -> io::Result<Vec<ConvertedExercise>> {
+        let mut exercises: Vec<ConvertedExercise> = vec![];
+        for (line_number, line) in csv.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("PuzzleId") {
+                continue;
+            }
+            let columns: Vec<&str> = line.split(',').collect();
+            if columns.len() < 8 {
+                skip_row(line_number, "expected at least 8 columns");
+                continue;
+            }
+            let rating: u32 = match columns[3].parse() {
+                Ok(rating) => rating,
+                Err(_) => {
+                    skip_row(line_number, "invalid rating");
+                    continue;
+                }
+            };
+            if !filter.accepts(rating, columns[7]) {
+                continue;
+            }
+            match self
+                .convert_lichess_puzzle(columns[0], columns[1], columns[2], columns[3], columns[7])
+            {
+                Ok(exercise) => exercises.push(exercise),
+                Err(error) => skip_row(line_number, error),
+            }
+        }
+        Ok(exercises)
+    }
+
+    fn convert_lichess_puzzle(
+        &mut self,
+        id: &str,
+        fen: &str,
+        moves: &str,
+        rating: &str,
+        themes: &str,
+    ) -> Result<ConvertedExercise, &'static str> {
+        let mut board = Board::from_fen(fen).map_err(|_| "invalid FEN")?;
+        let mut moves = moves.split_whitespace();
+        // The FEN is the position before the opponent's last move, which sets up the puzzle.
+        let setup_move = moves.next().ok_or("missing moves")?;
+        let setup_move = find_uci_move(&board, setup_move).ok_or("illegal setup move")?;
+        board.apply_move(setup_move);
+        // The whole solution is checked before the puzzle is converted, so a bad row is not counted as an exercise.
+        let mut solution_board = board.shallow_clone();
+        let mut solution: Vec<SanPlus> = vec![];
+        for uci_move in moves {
+            let bit_move =
+                find_uci_move(&solution_board, uci_move).ok_or("illegal move in the solution")?;
+            solution.push(san_plus_from_bit_move(&solution_board, bit_move));
+            solution_board.apply_move(bit_move);
+        }
+        self.begin_game();
+        self.begin_headers();
+        self.header(b"FEN", RawHeader(board.fen().as_bytes()));
+        self.header(b"PuzzleId", RawHeader(id.as_bytes()));
+        self.header(b"Rating", RawHeader(rating.as_bytes()));
+        self.header(b"Themes", RawHeader(themes.as_bytes()));
+        let _ = self.end_headers();
+        for san_plus in solution {
+            self.san(san_plus);
+        }
+        let description = self.end_game();
+        Ok(self.get_converted_exercise(description).unwrap())
+    }
+}
+
+fn skip_row(line_number: usize, error: &str) {
+    eprintln!(
+        "skipping line {} of the puzzle file: {}",
+        line_number + 1,
+        error
+    );
+}
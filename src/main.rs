@@ -15,12 +15,17 @@
 //     limitations under the License.
 
 extern crate blindfold_chess;
-use blindfold_chess::PositionConverter;
+use blindfold_chess::{
+    join_ssml_documents, AudioExporter, BrailleFormat, ConvertedExercise, Engine, EpubExporter,
+    LichessFilter, MoveOrigin, PgnAnnotator, PieceListStyle, PositionConverter, SolutionLayout,
+    VariationStyle, Verbosity,
+};
 use clap::{App, Arg};
 use pgn_reader::BufferedReader;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
+use std::time::Duration;
 
 fn main() -> std::io::Result<()> {
     let matches = App::new("Blindfold chess")
@@ -51,11 +56,251 @@ fn main() -> std::io::Result<()> {
                 .long("with_side_lines")
                 .help("If set, includes side lines in converted positions"),
         )
+        .arg(
+            Arg::with_name("max_variation_depth")
+                .long("max_variation_depth")
+                .takes_value(true)
+                .help("Leaves out side lines nested deeper than this many levels"),
+        )
+        .arg(
+            Arg::with_name("max_variation_moves")
+                .long("max_variation_moves")
+                .takes_value(true)
+                .help("Leaves out the moves of side lines past this many plies"),
+        )
+        .arg(
+            Arg::with_name("only_commented_variations")
+                .long("only_commented_variations")
+                .help("If set, leaves out side lines without comments"),
+        )
+        .arg(
+            Arg::with_name("with_material")
+                .short("m")
+                .long("with_material")
+                .help("If set, adds a material and imbalance summary before listing the pieces"),
+        )
+        .arg(
+            Arg::with_name("with_features")
+                .short("f")
+                .long("with_features")
+                .help("If set, adds key positional features such as pawn structure, open files, pins and hanging pieces"),
+        )
+        .arg(
+            Arg::with_name("with_square_hints")
+                .long("with_square_hints")
+                .help("If set, annotates pieces with their square colour, diagonals and nearby corner"),
+        )
+        .arg(
+            Arg::with_name("with_attacks")
+                .short("a")
+                .long("with_attacks")
+                .help("If set, lists the squares each piece attacks, the pieces it threatens and its defenders"),
+        )
+        .arg(
+            Arg::with_name("with_legal_moves")
+                .short("l")
+                .long("with_legal_moves")
+                .help("If set, lists every legal move of the side to move, grouped by piece"),
+        )
+        .arg(
+            Arg::with_name("engine")
+                .short("e")
+                .long("engine")
+                .help("If set, analyses each position with a UCI engine, by default stockfish from the PATH"),
+        )
+        .arg(
+            Arg::with_name("engine_path")
+                .long("engine_path")
+                .takes_value(true)
+                .help("Path of the UCI engine to analyse positions with. Implies --engine"),
+        )
+        .arg(
+            Arg::with_name("engine_depth")
+                .long("engine_depth")
+                .takes_value(true)
+                .default_value("18")
+                .help("Search depth of the engine analysis, in plies"),
+        )
+        .arg(
+            Arg::with_name("engine_time")
+                .long("engine_time")
+                .takes_value(true)
+                .default_value("30")
+                .help("Seconds the engine may search each position before it is told to stop"),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .help("If set, checks the solution of every exercise and writes a report of suspicious ones instead of the converted positions"),
+        )
+        .arg(
+            Arg::with_name("with_computed_solutions")
+                .long("with_computed_solutions")
+                .help("If set, searches for a mate in exercises without solution and adds it as a computed solution"),
+        )
+        .arg(
+            Arg::with_name("mate_depth")
+                .long("mate_depth")
+                .takes_value(true)
+                .default_value("3")
+                .help("How many moves deep the built-in mate search looks"),
+        )
+        .arg(
+            Arg::with_name("headers")
+                .long("headers")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("Comma separated pgn headers to read out before each exercise, besides Goal, Themes, Rating and Annotator"),
+        )
+        .arg(
+            Arg::with_name("input_format")
+                .long("input_format")
+                .takes_value(true)
+                .possible_values(&["pgn", "lichess", "epd"])
+                .help("Format of the input file, by default lichess for .csv files, epd for .epd and .fen files and pgn otherwise"),
+        )
+        .arg(
+            Arg::with_name("output_format")
+                .long("output_format")
+                .takes_value(true)
+                .possible_values(&[
+                    "text",
+                    "pgn",
+                    "ssml",
+                    "audio",
+                    "epub",
+                    "braille",
+                    "unicode_braille",
+                    "brf",
+                ])
+                .default_value("text")
+                .help("Whether to write plain text descriptions, the pgn input annotated with them in comments, SSML for text-to-speech engines, audio files with an M3U playlist written to OUTPUT, an EPUB 3 book, or terse braille as text, Unicode braille or a BRF file"),
+        )
+        .arg(
+            Arg::with_name("braille_width")
+                .long("braille_width")
+                .takes_value(true)
+                .default_value("40")
+                .help("Number of cells of the braille display, E.G. 20 or 40. Longer lines of braille output are wrapped"),
+        )
+        .arg(
+            Arg::with_name("title")
+                .long("title")
+                .takes_value(true)
+                .help("Title of the EPUB book, by default the name of the input file"),
+        )
+        .arg(
+            Arg::with_name("language")
+                .long("language")
+                .takes_value(true)
+                .default_value("en")
+                .help("Language of the EPUB book, as a BCP 47 tag, E.G. en or en-GB"),
+        )
+        .arg(
+            Arg::with_name("tts_program")
+                .long("tts_program")
+                .takes_value(true)
+                .default_value("espeak-ng")
+                .help("Text-to-speech program of the audio output. It reads text on its standard input"),
+        )
+        .arg(
+            Arg::with_name("tts_arg")
+                .long("tts_arg")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .help("An argument of the text-to-speech program, repeated for each argument, E.G. --tts_arg=-w --tts_arg={output}. The audio is written to {output}, or read from the standard output of the program if {output} is missing. By default, the arguments of espeak-ng"),
+        )
+        .arg(
+            Arg::with_name("tts_ssml")
+                .long("tts_ssml")
+                .help("If set, the text-to-speech command is given SSML instead of plain text"),
+        )
+        .arg(
+            Arg::with_name("audio_format")
+                .long("audio_format")
+                .takes_value(true)
+                .possible_values(&["wav", "ogg"])
+                .default_value("wav")
+                .help("Extension of the audio files, which should match what the text-to-speech command writes"),
+        )
+        .arg(
+            Arg::with_name("separate_solutions")
+                .long("separate_solutions")
+                .help("If set, the audio output puts solutions in their own tracks"),
+        )
+        .arg(
+            Arg::with_name("min_rating")
+                .long("min_rating")
+                .takes_value(true)
+                .help("Only converts lichess puzzles rated at least this much"),
+        )
+        .arg(
+            Arg::with_name("max_rating")
+                .long("max_rating")
+                .takes_value(true)
+                .help("Only converts lichess puzzles rated at most this much"),
+        )
+        .arg(
+            Arg::with_name("themes")
+                .long("themes")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("Comma separated themes, E.G. fork,pin. Only converts lichess puzzles with at least one of them"),
+        )
+        .arg(
+            Arg::with_name("verbosity")
+                .short("v")
+                .long("verbosity")
+                .takes_value(true)
+                .possible_values(&["terse", "normal", "verbose"])
+                .default_value("normal")
+                .help("How much is said about pieces, moves and sections: \"Rook g1\", \"Rook Gustav1\" or \"White rook on Gustav1\""),
+        )
+        .arg(
+            Arg::with_name("move_origin")
+                .long("move_origin")
+                .takes_value(true)
+                .possible_values(&["when_helpful", "always"])
+                .default_value("when_helpful")
+                .help("Whether moves always say where the piece comes from, or only when another piece could reach the same square"),
+        )
+        .arg(
+            Arg::with_name("solution_layout")
+                .long("solution_layout")
+                .takes_value(true)
+                .possible_values(&["ply", "pair", "paragraph"])
+                .default_value("pair")
+                .help("Writes solutions with one ply per line, a move of White and the answer of Black per line, or as a single paragraph"),
+        )
+        .arg(
+            Arg::with_name("variation_style")
+                .long("variation_style")
+                .takes_value(true)
+                .possible_values(&["spoken", "indented"])
+                .default_value("spoken")
+                .help("Announces where side lines begin and end, E.G. \"Variation 1 for White's move 2\" and \"End of variation\", or indents them"),
+        )
+        .arg(
+            Arg::with_name("with_side_prefixes")
+                .long("with_side_prefixes")
+                .help("Starts each move of solutions with \"White:\" or \"Black:\""),
+        )
+        .arg(
+            Arg::with_name("piece_list")
+                .short("p")
+                .long("piece_list")
+                .takes_value(true)
+                .possible_values(&["lines", "compact", "summary"])
+                .default_value("lines")
+                .help("How pieces are listed: one per line, grouped by type, or grouped with pawn summaries"),
+        )
         .get_matches();
 
     let read_path = Path::new(matches.value_of("INPUT").unwrap());
     let input_display = read_path.display();
-    let mut input_file = match File::open(&read_path) {
+    let mut input_file = match File::open(read_path) {
         Err(why) => panic!("couldn't open {}: {}", input_display, why),
         Ok(file) => file,
     };
@@ -70,15 +315,208 @@ fn main() -> std::io::Result<()> {
         with_comments = true;
     }
     let mut position_converter = PositionConverter::new_with_config(with_side_lines, with_comments);
-    let mut reader = BufferedReader::new_cursor(&buffer[..]);
-    let mut description = String::new();
-    while let Some(single_exercise) = reader.read_game(&mut position_converter)? {
-        description.push_str(&single_exercise);
+    if let Some(max_variation_depth) = matches.value_of("max_variation_depth") {
+        match max_variation_depth.parse() {
+            Err(why) => panic!("invalid maximum variation depth: {}", why),
+            Ok(max_variation_depth) => {
+                position_converter.set_max_variation_depth(Some(max_variation_depth))
+            }
+        }
+    }
+    if let Some(max_variation_moves) = matches.value_of("max_variation_moves") {
+        match max_variation_moves.parse() {
+            Err(why) => panic!("invalid maximum variation moves: {}", why),
+            Ok(max_variation_moves) => {
+                position_converter.set_max_variation_moves(Some(max_variation_moves))
+            }
+        }
+    }
+    position_converter
+        .set_only_commented_variations(matches.occurrences_of("only_commented_variations") > 0);
+    match matches.value_of("piece_list").unwrap() {
+        "compact" => position_converter.set_piece_list_style(PieceListStyle::Compact),
+        "summary" => position_converter.set_piece_list_style(PieceListStyle::Summary),
+        _ => position_converter.set_piece_list_style(PieceListStyle::Lines),
+    }
+    match matches.value_of("verbosity").unwrap() {
+        "terse" => position_converter.set_verbosity(Verbosity::Terse),
+        "verbose" => position_converter.set_verbosity(Verbosity::Verbose),
+        _ => position_converter.set_verbosity(Verbosity::Normal),
+    }
+    match matches.value_of("move_origin").unwrap() {
+        "always" => position_converter.set_move_origin(MoveOrigin::Always),
+        _ => position_converter.set_move_origin(MoveOrigin::WhenHelpful),
+    }
+    match matches.value_of("solution_layout").unwrap() {
+        "ply" => position_converter.set_solution_layout(SolutionLayout::PlyPerLine),
+        "paragraph" => position_converter.set_solution_layout(SolutionLayout::Paragraph),
+        _ => position_converter.set_solution_layout(SolutionLayout::PairPerLine),
+    }
+    match matches.value_of("variation_style").unwrap() {
+        "indented" => position_converter.set_variation_style(VariationStyle::Indented),
+        _ => position_converter.set_variation_style(VariationStyle::Spoken),
+    }
+    position_converter.set_with_side_prefixes(matches.occurrences_of("with_side_prefixes") > 0);
+    position_converter.set_with_material(matches.occurrences_of("with_material") > 0);
+    position_converter.set_with_features(matches.occurrences_of("with_features") > 0);
+    position_converter.set_with_square_hints(matches.occurrences_of("with_square_hints") > 0);
+    position_converter.set_with_attacks(matches.occurrences_of("with_attacks") > 0);
+    position_converter.set_with_legal_moves(matches.occurrences_of("with_legal_moves") > 0);
+    if let Some(headers) = matches.values_of("headers") {
+        position_converter.set_extra_headers(headers.map(String::from).collect());
     }
+    let output_format = matches.value_of("output_format").unwrap();
+    position_converter.set_ssml(
+        output_format == "ssml"
+            || (output_format == "audio" && matches.occurrences_of("tts_ssml") > 0),
+    );
+    match output_format {
+        "braille" => position_converter.set_braille(Some(BrailleFormat::Text)),
+        "unicode_braille" => position_converter.set_braille(Some(BrailleFormat::Unicode)),
+        "brf" => position_converter.set_braille(Some(BrailleFormat::Brf)),
+        _ => position_converter.set_braille(None),
+    }
+    match matches.value_of("braille_width").unwrap().parse() {
+        Err(why) => panic!("invalid braille width: {}", why),
+        Ok(width) => position_converter.set_braille_line_width(width),
+    }
+    position_converter.set_verify(matches.occurrences_of("verify") > 0);
+    position_converter
+        .set_with_computed_solutions(matches.occurrences_of("with_computed_solutions") > 0);
+    match matches.value_of("mate_depth").unwrap().parse() {
+        Err(why) => panic!("invalid mate depth: {}", why),
+        Ok(depth) => position_converter.set_mate_search_depth(depth),
+    }
+    if matches.occurrences_of("engine") > 0 || matches.is_present("engine_path") {
+        let engine_path = matches.value_of("engine_path").unwrap_or("stockfish");
+        let engine_depth: u32 = match matches.value_of("engine_depth").unwrap().parse() {
+            Err(why) => panic!("invalid engine depth: {}", why),
+            Ok(depth) => depth,
+        };
+        let engine_time: u64 = match matches.value_of("engine_time").unwrap().parse() {
+            Err(why) => panic!("invalid engine time: {}", why),
+            Ok(seconds) => seconds,
+        };
+        match Engine::start(engine_path, &[], engine_depth) {
+            Err(why) => panic!("couldn't start engine {}: {}", engine_path, why),
+            Ok(mut engine) => {
+                engine.set_time_limit(Duration::from_secs(engine_time));
+                position_converter.set_engine(engine);
+            }
+        }
+    }
+    let extension = read_path
+        .extension()
+        .and_then(|extension| extension.to_str());
+    let input_format = match (matches.value_of("input_format"), extension) {
+        (Some(input_format), _) => input_format,
+        (None, Some("csv")) => "lichess",
+        (None, Some("epd")) | (None, Some("fen")) => "epd",
+        _ => "pgn",
+    };
+    // Annotated pgn output writes the descriptions into the pgn games they come from.
+    if output_format == "pgn" && input_format != "pgn" {
+        panic!(
+            "--output_format pgn needs pgn input, but the input format is {}",
+            input_format
+        );
+    }
+    // The descriptions of all games, and the exercises among them with their numbers.
+    let mut descriptions: Vec<String> = vec![];
+    let mut exercises: Vec<ConvertedExercise> = vec![];
+    if input_format == "lichess" {
+        let mut filter = LichessFilter::default();
+        if let Some(min_rating) = matches.value_of("min_rating") {
+            match min_rating.parse() {
+                Err(why) => panic!("invalid minimum rating: {}", why),
+                Ok(min_rating) => filter.min_rating = Some(min_rating),
+            }
+        }
+        if let Some(max_rating) = matches.value_of("max_rating") {
+            match max_rating.parse() {
+                Err(why) => panic!("invalid maximum rating: {}", why),
+                Ok(max_rating) => filter.max_rating = Some(max_rating),
+            }
+        }
+        if let Some(themes) = matches.values_of("themes") {
+            filter.themes = themes.map(String::from).collect();
+        }
+        let csv = String::from_utf8_lossy(&buffer);
+        exercises = position_converter.convert_lichess_csv(&csv, &filter)?;
+        descriptions = exercises
+            .iter()
+            .map(|exercise| exercise.description.clone())
+            .collect();
+    } else if input_format == "epd" {
+        let epd = String::from_utf8_lossy(&buffer);
+        exercises = position_converter.convert_epd(&epd)?;
+        descriptions = exercises
+            .iter()
+            .map(|exercise| exercise.description.clone())
+            .collect();
+    } else if output_format == "pgn" {
+        let mut reader = BufferedReader::new_cursor(&buffer[..]);
+        let mut annotator = PgnAnnotator::new(position_converter);
+        while let Some(single_game) = reader.read_game(&mut annotator)? {
+            descriptions.push(single_game);
+        }
+    } else {
+        let mut reader = BufferedReader::new_cursor(&buffer[..]);
+        while let Some(single_game) = reader.read_game(&mut position_converter)? {
+            if let Some(exercise) = position_converter.get_converted_exercise(single_game.clone()) {
+                exercises.push(exercise);
+            }
+            descriptions.push(single_game);
+        }
+    }
+    // Games without exercise and exercises without issues in verify mode have no description.
+    exercises.retain(|exercise| !exercise.description.is_empty());
 
     let output_path = Path::new(matches.value_of("OUTPUT").unwrap());
+    if output_format == "audio" {
+        let tts_program = matches.value_of("tts_program").unwrap();
+        let tts_args: Vec<&str> = match matches.values_of("tts_arg") {
+            Some(tts_args) => tts_args.collect(),
+            None if matches.occurrences_of("tts_program") == 0 => vec!["--stdin", "-w", "{output}"],
+            None => vec![],
+        };
+        let mut audio_exporter = AudioExporter::new(tts_program, &tts_args);
+        audio_exporter.set_extension(matches.value_of("audio_format").unwrap());
+        audio_exporter.set_separate_solutions(matches.occurrences_of("separate_solutions") > 0);
+        match audio_exporter.export(&exercises, output_path) {
+            Err(why) => panic!(
+                "couldn't write audio for {}: {}",
+                output_path.display(),
+                why
+            ),
+            Ok(_) => println!("successfully wrote to {}", output_path.display()),
+        }
+        return Ok(());
+    }
+    if output_format == "epub" {
+        let title = match matches.value_of("title") {
+            Some(title) => title.to_string(),
+            None => read_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        let mut epub_exporter = EpubExporter::new(&title);
+        epub_exporter.set_language(matches.value_of("language").unwrap());
+        match epub_exporter.export(&exercises, output_path) {
+            Err(why) => panic!("couldn't write {}: {}", output_path.display(), why),
+            Ok(_) => println!("successfully wrote to {}", output_path.display()),
+        }
+        return Ok(());
+    }
+    // SSML output is a single document, so the documents of the exercises are joined under one root.
+    let description = if output_format == "ssml" {
+        join_ssml_documents(&descriptions)
+    } else {
+        descriptions.concat()
+    };
     let output_display = output_path.display();
-    let mut output_file = match File::create(&output_path) {
+    let mut output_file = match File::create(output_path) {
         Err(why) => panic!("couldn't create {}: {}", output_display, why),
         Ok(file) => file,
     };
@@ -0,0 +1,116 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// A small brute force mate search over tanton's move generator, meant for short "mate in N" exercises.
+
+use super::layout::SolutionItem;
+use super::PositionConverter;
+use tanton::board::Board;
+use tanton::core::piece_move::BitMove;
+use tanton::core::Player;
+
+impl PositionConverter {
+    // Describes the moves of a mate found by the search for an exercise without solution, E.G.
+    // "1. Rook Ana8 checkmate\n". The moves are laid out like the moves of a pgn solution.
+    pub(crate) fn describe_computed_solution(&self, board: &Board, line: &[BitMove]) -> String {
+        let first_ply: usize = match board.turn() {
+            Player::White => 1,
+            Player::Black => 2,
+        };
+        let mut board = board.shallow_clone();
+        let mut items: Vec<SolutionItem> = vec![];
+        for (index, bit_move) in line.iter().enumerate() {
+            items.push(SolutionItem::Move {
+                ply: first_ply + index,
+                description: self.describe_bit_move(&board, *bit_move),
+            });
+            board.apply_move(*bit_move);
+        }
+        self.render_solution(&items)
+    }
+}
+
+// Finds the shortest forced mate for the side to move in at most |max_moves| moves.
+// Returns the mating line, where the defender always plays the reply that resists the longest.
+pub(crate) fn find_mate(board: &Board, max_moves: usize) -> Option<Vec<BitMove>> {
+    let mut board = board.shallow_clone();
+    (1..=max_moves).find_map(|moves| mate_line(&mut board, moves))
+}
+
+// Returns every first move of the side to move that forces mate in at most |moves| moves.
+pub(crate) fn find_mating_moves(board: &Board, moves: usize) -> Vec<BitMove> {
+    let mut board = board.shallow_clone();
+    let candidates: Vec<BitMove> = board.generate_moves().iter().cloned().collect();
+    candidates
+        .into_iter()
+        .filter(|candidate| forced_mate_after(&mut board, *candidate, moves).is_some())
+        .collect()
+}
+
+// Returns whether |first_move| forces mate in at most |moves| moves.
+pub(crate) fn forces_mate(board: &Board, first_move: BitMove, moves: usize) -> bool {
+    let mut board = board.shallow_clone();
+    forced_mate_after(&mut board, first_move, moves).is_some()
+}
+
+fn mate_line(board: &mut Board, moves: usize) -> Option<Vec<BitMove>> {
+    let candidates: Vec<BitMove> = board.generate_moves().iter().cloned().collect();
+    candidates
+        .into_iter()
+        .find_map(|candidate| forced_mate_after(board, candidate, moves))
+}
+
+// Plays |first_move| and checks that every reply of the defender still gets mated in time.
+// |board| is left as it was before the call.
+fn forced_mate_after(board: &mut Board, first_move: BitMove, moves: usize) -> Option<Vec<BitMove>> {
+    // The last move of a mate must give check, which saves searching all quiet moves.
+    if moves == 1 && !board.gives_check(first_move) {
+        return None;
+    }
+    board.apply_move(first_move);
+    let mut line = None;
+    if board.checkmate() {
+        line = Some(vec![first_move]);
+    } else if moves > 1 && !board.stalemate() {
+        let replies: Vec<BitMove> = board.generate_moves().iter().cloned().collect();
+        let mut longest_defence: Vec<BitMove> = vec![];
+        let mut all_replies_mated = true;
+        for reply in replies.into_iter() {
+            board.apply_move(reply);
+            let continuation = mate_line(board, moves - 1);
+            board.undo_move();
+            match continuation {
+                Some(continuation) => {
+                    if continuation.len() + 1 > longest_defence.len() {
+                        longest_defence = vec![reply];
+                        longest_defence.extend(continuation);
+                    }
+                }
+                None => {
+                    all_replies_mated = false;
+                    break;
+                }
+            }
+        }
+        if all_replies_mated {
+            let mut mating_line = vec![first_move];
+            mating_line.extend(longest_defence);
+            line = Some(mating_line);
+        }
+    }
+    board.undo_move();
+    line
+}
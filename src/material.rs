@@ -0,0 +1,195 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Material count and imbalance summary of a position, E.G.
+// "Material: White has rook and two pawns against bishop and three pawns; White is up the exchange".
+
+use super::{join_with_and, PositionConverter};
+use std::collections::HashMap;
+use std::fmt::Write;
+use tanton::core::sq::SQ;
+
+// Pieces that count as material, from the most to the least valuable, as used in FEN.
+const MATERIAL_PIECES: &str = "qrbnp";
+
+impl PositionConverter {
+    pub(crate) fn describe_material(
+        &self,
+        description: &mut String,
+        piece_to_squares: &HashMap<char, Vec<SQ>>,
+    ) {
+        let white = self.count_material(true, piece_to_squares);
+        let black = self.count_material(false, piece_to_squares);
+        if white == black {
+            writeln!(description, "Material: equal").unwrap();
+        } else {
+            writeln!(
+                description,
+                "Material: White has {} against {}; {}",
+                self.describe_material_list(&white),
+                self.describe_material_list(&black),
+                self.describe_material_balance(&white, &black)
+            )
+            .unwrap();
+        }
+        let imbalances = self.describe_imbalances(piece_to_squares);
+        if !imbalances.is_empty() {
+            writeln!(description, "Imbalances: {}", imbalances.join("; ")).unwrap();
+        }
+    }
+
+    // Returns how many pieces of each type in |MATERIAL_PIECES| one side has.
+    fn count_material(&self, white: bool, piece_to_squares: &HashMap<char, Vec<SQ>>) -> Vec<usize> {
+        MATERIAL_PIECES
+            .chars()
+            .map(|piece| {
+                let piece_with_color = if white {
+                    piece.to_ascii_uppercase()
+                } else {
+                    piece
+                };
+                piece_to_squares
+                    .get(&piece_with_color)
+                    .map_or(0, |squares| squares.len())
+            })
+            .collect()
+    }
+
+    // Describes the material of one side, E.G. "rook and two pawns".
+    fn describe_material_list(&self, counts: &[usize]) -> String {
+        let mut items: Vec<String> = vec![];
+        for (piece, count) in MATERIAL_PIECES.chars().zip(counts.iter()) {
+            let piece = piece.to_string();
+            match count {
+                0 => continue,
+                1 => items.push(self.get_full_piece_name(&piece).to_lowercase()),
+                _ => items.push(format!(
+                    "{} {}",
+                    self.get_count_name(*count),
+                    self.get_full_piece_plural_name(&piece).to_lowercase()
+                )),
+            }
+        }
+        if items.is_empty() {
+            return String::from("only the king");
+        }
+        join_with_and(&items)
+    }
+
+    // Describes who is ahead in material, counting pawns as 1, minor pieces as 3, rooks as 5 and queens as 9.
+    fn describe_material_balance(&self, white: &[usize], black: &[usize]) -> String {
+        let values = [9, 5, 3, 3, 1];
+        let points = |counts: &[usize]| -> i32 {
+            counts
+                .iter()
+                .zip(values.iter())
+                .map(|(count, value)| *count as i32 * value)
+                .sum()
+        };
+        let difference = |index: usize| white[index] as i32 - black[index] as i32;
+        let rooks = difference(1);
+        let minors = difference(2) + difference(3);
+        let pawns = difference(4);
+        let (leader, sign) = if points(white) >= points(black) {
+            ("White", 1)
+        } else {
+            ("Black", -1)
+        };
+        if difference(0) == 0 && rooks.abs() == 1 && minors == -rooks {
+            let leader = if rooks > 0 { "White" } else { "Black" };
+            return format!("{} is up the exchange", leader);
+        }
+        if difference(0) == 0 && rooks == 0 && minors == 0 && pawns != 0 {
+            return match (pawns * sign) as usize {
+                1 => format!("{} is up a pawn", leader),
+                count => format!("{} is up {} pawns", leader, self.get_count_name(count)),
+            };
+        }
+        match (points(white) - points(black)) * sign {
+            0 => String::from("material is balanced"),
+            3 if pawns == 0 && minors * sign == 1 => format!("{} is up a piece", leader),
+            advantage => format!(
+                "{} is ahead by {} points",
+                leader,
+                self.get_count_name(advantage as usize)
+            ),
+        }
+    }
+
+    // Lists the bishop pair, opposite-coloured bishops and passed pawns of the position.
+    fn describe_imbalances(&self, piece_to_squares: &HashMap<char, Vec<SQ>>) -> Vec<String> {
+        let mut imbalances: Vec<String> = vec![];
+        let no_squares: Vec<SQ> = vec![];
+        let white_bishops = piece_to_squares.get(&'B').unwrap_or(&no_squares);
+        let black_bishops = piece_to_squares.get(&'b').unwrap_or(&no_squares);
+        let has_bishop_pair = |bishops: &Vec<SQ>| {
+            bishops.iter().any(|square| square.on_light_square())
+                && bishops.iter().any(|square| square.on_dark_square())
+        };
+        match (
+            has_bishop_pair(white_bishops),
+            has_bishop_pair(black_bishops),
+        ) {
+            (true, false) => imbalances.push(String::from("White has the bishop pair")),
+            (false, true) => imbalances.push(String::from("Black has the bishop pair")),
+            _ => {}
+        }
+        if white_bishops.len() == 1
+            && black_bishops.len() == 1
+            && white_bishops[0].opposite_colors(black_bishops[0])
+        {
+            imbalances.push(String::from("opposite-coloured bishops"));
+        }
+        let white_pawns = piece_to_squares.get(&'P').unwrap_or(&no_squares);
+        let black_pawns = piece_to_squares.get(&'p').unwrap_or(&no_squares);
+        for (side, pawns, opponent_pawns, white) in [
+            ("White", white_pawns, black_pawns, true),
+            ("Black", black_pawns, white_pawns, false),
+        ]
+        .iter()
+        {
+            let passed: Vec<String> = pawns
+                .iter()
+                .filter(|pawn| is_passed_pawn(**pawn, opponent_pawns, *white))
+                .map(|pawn| self.describe_square(pawn))
+                .collect();
+            match passed.len() {
+                0 => {}
+                1 => imbalances.push(format!("{} has a passed pawn on {}", side, passed[0])),
+                _ => imbalances.push(format!(
+                    "{} has passed pawns on {}",
+                    side,
+                    passed.join(", ")
+                )),
+            }
+        }
+        imbalances
+    }
+}
+
+// A pawn is passed when no opponent pawn stands in front of it on its own or an adjacent file.
+pub(crate) fn is_passed_pawn(pawn: SQ, opponent_pawns: &[SQ], white: bool) -> bool {
+    let file = pawn.file_idx_of_sq() as i8;
+    let rank = pawn.rank_idx_of_sq();
+    !opponent_pawns.iter().any(|opponent| {
+        let in_front = if white {
+            opponent.rank_idx_of_sq() > rank
+        } else {
+            opponent.rank_idx_of_sq() < rank
+        };
+        in_front && (opponent.file_idx_of_sq() as i8 - file).abs() <= 1
+    })
+}
@@ -0,0 +1,299 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Converts moves generated by tanton into the same accessible-friendly phrasing used for pgn moves.
+
+use super::{PositionConverter, Verbosity};
+use pgn_reader::{CastlingSide, File, Rank, Role, San, SanPlus, Square};
+use shakmaty::san::Suffix;
+use std::fmt::Write;
+use std::io;
+use tanton::board::Board;
+use tanton::core::piece_move::BitMove;
+use tanton::core::sq::SQ;
+use tanton::core::{PieceType, Player};
+
+impl PositionConverter {
+    // Lists every legal move of the position described by |fen|, one line per piece, E.G.
+    // "Rook Gustav1: Rook Ana1, Rook Gustav8 check".
+    pub fn describe_legal_moves(&self, fen: &str) -> io::Result<String> {
+        let board = board_from_fen(fen)?;
+        Ok(self.describe_legal_moves_of_board(&board))
+    }
+
+    // Describes moves in UCI notation played one after the other from the position described by |fen|,
+    // phrased like pgn moves and engine lines, E.G. ["e2e4", "e7e5"] becomes ["Eva4", "Eva5"].
+    pub fn describe_uci_moves(&self, fen: &str, uci_moves: &[&str]) -> io::Result<Vec<String>> {
+        let mut board = board_from_fen(fen)?;
+        let mut descriptions: Vec<String> = vec![];
+        for uci_move in uci_moves.iter() {
+            let bit_move = find_uci_move(&board, uci_move).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("illegal move {} in position {}", uci_move, board.fen()),
+                )
+            })?;
+            descriptions.push(self.describe_bit_move(&board, bit_move));
+            board.apply_move(bit_move);
+        }
+        Ok(descriptions)
+    }
+
+    pub(crate) fn describe_legal_moves_of_board(&self, board: &Board) -> String {
+        let mut description = String::new();
+        let mut moves: Vec<BitMove> = board.generate_moves().iter().cloned().collect();
+        if moves.is_empty() {
+            writeln!(description, "none").unwrap();
+            return description;
+        }
+        // Groups moves by piece, in the same order as pieces are listed in the position description.
+        moves.sort_by_key(|bit_move| {
+            (
+                board.moved_piece(*bit_move).type_of() as u8,
+                bit_move.get_src_u8(),
+            )
+        });
+        let mut current_piece: Option<SQ> = None;
+        for bit_move in moves.iter() {
+            let src = bit_move.get_src();
+            if current_piece != Some(src) {
+                if current_piece.is_some() {
+                    writeln!(description).unwrap();
+                }
+                current_piece = Some(src);
+                let piece_type = board.piece_at_sq(src).type_of();
+                write!(
+                    description,
+                    "{} {}: ",
+                    self.get_piece_name(&piece_type.char_lower().to_string()),
+                    self.describe_square(&src)
+                )
+                .unwrap();
+            } else {
+                write!(description, ", ").unwrap();
+            }
+            write!(description, "{}", self.describe_bit_move(board, *bit_move)).unwrap();
+        }
+        writeln!(description).unwrap();
+        description
+    }
+
+    // Describes a move of |board| like a pgn move, adding whether it gives check or mate.
+    pub(crate) fn describe_bit_move(&self, board: &Board, bit_move: BitMove) -> String {
+        if self.verbosity == Verbosity::Verbose {
+            let white = board.turn() == Player::White;
+            return self.describe_verbose_move(
+                Some(board),
+                &san_from_bit_move(board, bit_move),
+                white,
+            );
+        }
+        let mut move_description = self.describe_san_from(
+            &san_from_bit_move(board, bit_move),
+            Some(bit_move.get_src()),
+        );
+        let mut board_after_move = board.shallow_clone();
+        board_after_move.apply_move(bit_move);
+        if self.braille.is_some() {
+            // Braille keeps SAN suffixes, E.G. "Ra8#".
+            if board_after_move.checkmate() {
+                move_description.push('#');
+            } else if board_after_move.in_check() {
+                move_description.push('+');
+            }
+        } else if board_after_move.checkmate() {
+            write!(move_description, " checkmate").unwrap();
+        } else if board_after_move.in_check() {
+            write!(move_description, " check").unwrap();
+        }
+        move_description
+    }
+
+    // Describes moves in UCI notation played one after the other from |board|, numbered like a solution, E.G.
+    // "1. Rook Gustav8 check King takes Gustav8". Stops at the first move that is not legal.
+    pub(crate) fn describe_uci_line(&self, board: &Board, uci_moves: &[String]) -> String {
+        let mut board = board.shallow_clone();
+        let first_ply: usize = match board.turn() {
+            Player::White => 0,
+            Player::Black => 1,
+        };
+        let mut line: Vec<String> = vec![];
+        if first_ply == 1 {
+            line.push(String::from("1..."));
+        }
+        for (index, uci_move) in uci_moves.iter().enumerate() {
+            let bit_move = match find_uci_move(&board, uci_move) {
+                Some(bit_move) => bit_move,
+                None => break,
+            };
+            let ply_count = first_ply + index;
+            if ply_count.is_multiple_of(2) {
+                line.push(format!("{}.", ply_count / 2 + 1));
+            }
+            line.push(self.describe_bit_move(&board, bit_move));
+            board.apply_move(bit_move);
+        }
+        line.join(" ")
+    }
+}
+
+fn board_from_fen(fen: &str) -> io::Result<Board> {
+    Board::from_fen(fen).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid FEN {}: {:?}", fen, error),
+        )
+    })
+}
+
+// Returns the position after playing |san| on |board|, or None if there is no position or the move is not legal.
+pub(crate) fn play_san(board: Option<&Board>, san: &San) -> Option<Board> {
+    board.and_then(|board| {
+        find_san_move(board, san).map(|bit_move| {
+            let mut next_board = board.shallow_clone();
+            next_board.apply_move(bit_move);
+            next_board
+        })
+    })
+}
+
+// Finds the legal move of |board| written as |uci_move|, E.G. "e2e4" or "e7e8q".
+pub(crate) fn find_uci_move(board: &Board, uci_move: &str) -> Option<BitMove> {
+    board
+        .generate_moves()
+        .iter()
+        .find(|bit_move| bit_move.stringify() == uci_move)
+        .cloned()
+}
+
+// Finds the legal move of |board| written as |san|. Returns None if no move or more than one move matches.
+pub(crate) fn find_san_move(board: &Board, san: &San) -> Option<BitMove> {
+    let candidates: Vec<BitMove> = board
+        .generate_moves()
+        .iter()
+        .filter(|bit_move| match *san {
+            San::Castle(CastlingSide::KingSide) => bit_move.is_king_castle(),
+            San::Castle(CastlingSide::QueenSide) => bit_move.is_queen_castle(),
+            San::Normal {
+                role,
+                file,
+                rank,
+                to,
+                promotion,
+                ..
+            } => {
+                let src = bit_move.get_src();
+                !bit_move.is_castle()
+                    && board.moved_piece(**bit_move).type_of().char_lower() == role.char()
+                    && bit_move.get_dest_u8() as u32 == to as u32
+                    && file.is_none_or(|file| file as u8 == src.file_idx_of_sq())
+                    && rank.is_none_or(|rank| rank as u8 == src.rank_idx_of_sq())
+                    && match promotion {
+                        Some(promotion) => {
+                            bit_move.is_promo()
+                                && bit_move.promo_piece().char_lower() == promotion.char()
+                        }
+                        None => !bit_move.is_promo(),
+                    }
+            }
+            _ => false,
+        })
+        .cloned()
+        .collect();
+    match candidates.len() {
+        1 => Some(candidates[0]),
+        _ => None,
+    }
+}
+
+// Builds the SAN of a legal move of |board|, disambiguating it from the other legal moves like pgn does.
+// Like |san_from_bit_move|, with the check or checkmate suffix of the position after the move, E.G. "Ra8#".
+pub(crate) fn san_plus_from_bit_move(board: &Board, bit_move: BitMove) -> SanPlus {
+    let mut board_after_move = board.shallow_clone();
+    board_after_move.apply_move(bit_move);
+    let suffix = if board_after_move.checkmate() {
+        Some(Suffix::Checkmate)
+    } else if board_after_move.in_check() {
+        Some(Suffix::Check)
+    } else {
+        None
+    };
+    SanPlus {
+        san: san_from_bit_move(board, bit_move),
+        suffix,
+    }
+}
+
+pub(crate) fn san_from_bit_move(board: &Board, bit_move: BitMove) -> San {
+    if bit_move.is_king_castle() {
+        return San::Castle(CastlingSide::KingSide);
+    }
+    if bit_move.is_queen_castle() {
+        return San::Castle(CastlingSide::QueenSide);
+    }
+    let src = bit_move.get_src();
+    let dest = bit_move.get_dest();
+    let piece_type = board.moved_piece(bit_move).type_of();
+    let capture = bit_move.is_capture();
+    let mut file = None;
+    let mut rank = None;
+    if piece_type == PieceType::P {
+        // Pawn captures always name the file the pawn comes from, E.G. "exd5".
+        if capture {
+            file = Some(File::new(src.file_idx_of_sq() as u32));
+        }
+    } else if piece_type != PieceType::K {
+        let rivals: Vec<SQ> = board
+            .generate_moves()
+            .iter()
+            .filter(|other| {
+                other.get_dest() == dest
+                    && other.get_src() != src
+                    && board.moved_piece(**other).type_of() == piece_type
+            })
+            .map(|other| other.get_src())
+            .collect();
+        if !rivals.is_empty() {
+            let same_file = rivals
+                .iter()
+                .any(|rival| rival.file_idx_of_sq() == src.file_idx_of_sq());
+            let same_rank = rivals
+                .iter()
+                .any(|rival| rival.rank_idx_of_sq() == src.rank_idx_of_sq());
+            if !same_file {
+                file = Some(File::new(src.file_idx_of_sq() as u32));
+            } else if !same_rank {
+                rank = Some(Rank::new(src.rank_idx_of_sq() as u32));
+            } else {
+                file = Some(File::new(src.file_idx_of_sq() as u32));
+                rank = Some(Rank::new(src.rank_idx_of_sq() as u32));
+            }
+        }
+    }
+    let promotion = if bit_move.is_promo() {
+        Role::from_char(bit_move.promo_piece().char_lower())
+    } else {
+        None
+    };
+    San::Normal {
+        role: Role::from_char(piece_type.char_lower()).unwrap(),
+        file,
+        rank,
+        capture,
+        to: Square::new(dest.0 as u32),
+        promotion,
+    }
+}
@@ -0,0 +1,210 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Writes the games back out as pgn, annotated with the accessible descriptions, E.G.
+// [FEN "7k/6pp/8/8/8/8/8/R5K1 w - - 0 1"]
+//
+// {Exercise 1: ...} 1. Ra8# {Rook Ana8} 1-0
+// Headers, comments, NAGs and variations of the original games are kept.
+
+use super::moves::play_san;
+use super::{get_starting_ply, PositionConverter};
+use pgn_reader::{Nag, Outcome, RawComment, RawHeader, SanPlus, Skip, Visitor};
+use std::fmt::Write;
+use std::str;
+use tanton::board::Board;
+
+pub struct PgnAnnotator {
+    // Describes the starting position of exercises, as configured by the caller.
+    converter: PositionConverter,
+    // The headers of the game being exported, already written in pgn syntax.
+    headers: String,
+    // The movetext of the game being exported.
+    movetext: String,
+    // A stack with the ply of the next move of the main line and of each variation being parsed.
+    // Plies count from the start of the game, so the first move of White is ply 0 and the first move of Black ply 1.
+    plies: Vec<usize>,
+    // The positions of the lines being parsed, one entry per value of |plies|: the position before the last move,
+    // where a side line starts from, and the current position. They tell the converter where pieces come from.
+    line_boards: Vec<(Option<Board>, Option<Board>)>,
+    // The FEN header of the game being exported, if any.
+    starting_fen: Option<String>,
+    // Whether the next move of Black needs its move number, E.G. "3... Nf6", because something came in between.
+    needs_move_number: bool,
+    // The spoken form of the last move, written as a comment once its NAGs are written.
+    pending_move_description: Option<String>,
+    // The Result header of the game being exported, used when the movetext has no result.
+    result: String,
+}
+
+impl PgnAnnotator {
+    pub fn new(converter: PositionConverter) -> PgnAnnotator {
+        PgnAnnotator {
+            converter,
+            headers: String::new(),
+            movetext: String::new(),
+            plies: vec![0],
+            line_boards: vec![(None, None)],
+            starting_fen: None,
+            needs_move_number: false,
+            pending_move_description: None,
+            result: String::from("*"),
+        }
+    }
+
+    fn push_token(&mut self, token: &str) {
+        if !self.movetext.is_empty() && !self.movetext.ends_with(['(', '\n']) {
+            self.movetext.push(' ');
+        }
+        self.movetext.push_str(token);
+    }
+
+    fn flush_move_description(&mut self) {
+        if let Some(move_description) = self.pending_move_description.take() {
+            self.push_comment(&move_description);
+        }
+    }
+
+    fn push_comment(&mut self, comment: &str) {
+        self.push_token(&pgn_comment(comment));
+        self.needs_move_number = true;
+    }
+}
+
+// Writes |comment| as a pgn comment, E.G. "{Rook Ana8}". A closing brace would end a brace comment early and pgn has
+// no escaping, so such comments are written as rest of line comments instead, E.G. ";Mate in {one}".
+fn pgn_comment(comment: &str) -> String {
+    let comment = comment.trim();
+    if !comment.contains('}') {
+        return format!("{{{}}}", comment);
+    }
+    comment.lines().map(|line| format!(";{}\n", line)).collect()
+}
+
+impl Visitor for PgnAnnotator {
+    type Result = String;
+
+    fn begin_game(&mut self) {
+        self.converter.begin_game();
+    }
+
+    fn begin_headers(&mut self) {
+        self.converter.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader) {
+        // The raw value keeps the escaping of the original pgn.
+        let key_str = String::from_utf8_lossy(key);
+        let value_str = String::from_utf8_lossy(value.as_bytes());
+        writeln!(self.headers, "[{} \"{}\"]", key_str, value_str).unwrap();
+        if key_str == "FEN" {
+            self.plies = vec![get_starting_ply(&value_str)];
+            self.starting_fen = Some(value_str.to_string());
+        } else if key_str == "Result" {
+            self.result = value_str.into_owned();
+        }
+        self.converter.header(key, RawHeader(value.as_bytes()));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let _ = self.converter.end_headers();
+        let board = match &self.starting_fen {
+            Some(fen) => Board::from_fen(fen).ok(),
+            None => Some(Board::start_pos()),
+        };
+        self.line_boards = vec![(None, board)];
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.flush_move_description();
+        let ply = *self.plies.last().unwrap();
+        if ply.is_multiple_of(2) {
+            self.push_token(&format!("{}.", ply / 2 + 1));
+        } else if self.needs_move_number || self.movetext.is_empty() {
+            self.push_token(&format!("{}...", ply / 2 + 1));
+        }
+        self.push_token(&san_plus.to_string());
+        *self.plies.last_mut().unwrap() += 1;
+        let line_board = self.line_boards.last_mut().unwrap();
+        self.pending_move_description = Some(self.converter.describe_move_on_board(
+            line_board.1.as_ref(),
+            &san_plus.san,
+            ply.is_multiple_of(2),
+        ));
+        let next_board = play_san(line_board.1.as_ref(), &san_plus.san);
+        line_board.0 = std::mem::replace(&mut line_board.1, next_board);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.push_token(&nag.to_string());
+    }
+
+    fn comment(&mut self, comment: RawComment) {
+        self.flush_move_description();
+        let comment_str = String::from_utf8_lossy(comment.as_bytes()).into_owned();
+        self.push_comment(&comment_str);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.flush_move_description();
+        // A variation replaces the last move of the line it branches from.
+        let ply = self.plies.last().unwrap().saturating_sub(1);
+        self.plies.push(ply);
+        let start_board = self.line_boards.last().unwrap().0.clone();
+        self.line_boards.push((None, start_board));
+        self.push_token("(");
+        self.needs_move_number = true;
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.flush_move_description();
+        self.plies.pop();
+        self.line_boards.pop();
+        self.movetext.push(')');
+        self.needs_move_number = true;
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        if let Some(outcome) = outcome {
+            self.result = outcome.to_string();
+        }
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.flush_move_description();
+        // No moves are passed to the converter, so it only describes the starting position of exercises.
+        let description = self.converter.end_game();
+        let mut pgn = std::mem::take(&mut self.headers);
+        pgn.push('\n');
+        if !description.is_empty() {
+            pgn.push_str(&pgn_comment(&description));
+            if !pgn.ends_with('\n') {
+                pgn.push('\n');
+            }
+        }
+        let result = std::mem::replace(&mut self.result, String::from("*"));
+        self.push_token(&result);
+        writeln!(pgn, "{}\n", self.movetext).unwrap();
+        self.movetext.clear();
+        self.plies = vec![0];
+        self.line_boards = vec![(None, None)];
+        self.starting_fen = None;
+        self.needs_move_number = false;
+        pgn
+    }
+}
@@ -0,0 +1,124 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Renders descriptions as SSML for text-to-speech engines, one <speak> document per exercise, joined under a
+// single root when written to one file, E.G.
+// <speak><p><s>Exercise 1:</s></p>...<say-as interpret-as="cardinal">1</say-as> Rook Ana 8<break time="600ms"/>...</speak>
+
+use pgn_reader::SanPlus;
+
+// Pause after each move of a solution, so listeners have time to follow it on their mental board.
+const MOVE_BREAK: &str = "<break time=\"600ms\"/>";
+
+// Renders plain description lines as SSML sentences.
+pub(crate) fn ssml_lines(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format!("<s>{}</s>", ssml_text(line)))
+        .collect()
+}
+
+// Renders piece lists, read slower than the rest as listeners build the position from them.
+pub(crate) fn ssml_piece_list(text: &str) -> String {
+    format!(
+        "<prosody rate=\"slow\"><p>{}</p></prosody>",
+        ssml_lines(text)
+    )
+}
+
+// Renders a move number, E.G. "3" for White or "3, Black" for a move of Black after a break in the line.
+pub(crate) fn ssml_move_number(move_count: usize, black: bool) -> String {
+    let number = format!("<say-as interpret-as=\"cardinal\">{}</say-as>", move_count);
+    if black {
+        format!("{}, Black", number)
+    } else {
+        number
+    }
+}
+
+// Renders a described move followed by a pause, emphasizing check and mate.
+pub(crate) fn ssml_move(move_description: &str, san_plus: &SanPlus) -> String {
+    // pgn_reader does not export the suffix type, so the suffix is read from the SAN text.
+    let san_text = san_plus.to_string();
+    // Verbose moves already say it, E.G. "White rook moves from Ana1 to Ana8, checkmate".
+    let described = move_description.ends_with("check") || move_description.ends_with("checkmate");
+    let suffix = if described {
+        ""
+    } else if san_text.ends_with('#') {
+        " <emphasis level=\"strong\">checkmate</emphasis>"
+    } else if san_text.ends_with('+') {
+        " <emphasis level=\"strong\">check</emphasis>"
+    } else {
+        ""
+    };
+    format!("{}{}{}", ssml_text(move_description), suffix, MOVE_BREAK)
+}
+
+// Wraps one exercise in its own document.
+pub(crate) fn ssml_document(body: &str) -> String {
+    format!("<speak>\n{}\n</speak>\n", body.trim_end())
+}
+
+// Joins the documents of several exercises into a single document, E.G. for one SSML file holding them all.
+pub fn join_ssml_documents(documents: &[String]) -> String {
+    let bodies: String = documents
+        .iter()
+        .filter(|document| !document.is_empty())
+        .map(|document| {
+            let body = document.trim();
+            let body = body.strip_prefix("<speak>").unwrap_or(body);
+            let body = body.strip_suffix("</speak>").unwrap_or(body);
+            format!("{}\n", body.trim())
+        })
+        .collect();
+    ssml_document(&bodies)
+}
+
+// Escapes |text| for XML and helps TTS engines with chess words: squares are split into file and rank,
+// E.G. "Hector8" becomes "Hector 8", and check and mate are emphasized.
+pub(crate) fn ssml_text(text: &str) -> String {
+    let mut spaced = String::new();
+    let mut previous: Option<char> = None;
+    for c in text.chars() {
+        if c.is_ascii_digit() && previous.is_some_and(|previous| previous.is_alphabetic()) {
+            spaced.push(' ');
+        }
+        match c {
+            '&' => spaced.push_str("&amp;"),
+            '<' => spaced.push_str("&lt;"),
+            '>' => spaced.push_str("&gt;"),
+            '"' => spaced.push_str("&quot;"),
+            '\'' => spaced.push_str("&apos;"),
+            c => spaced.push(c),
+        }
+        previous = Some(c);
+    }
+    spaced
+        .split(' ')
+        .map(|word| {
+            let bare_word = word.trim_end_matches(|c: char| c.is_ascii_punctuation());
+            match bare_word {
+                "check" | "checkmate" => format!(
+                    "<emphasis level=\"strong\">{}</emphasis>{}",
+                    bare_word,
+                    &word[bare_word.len()..]
+                ),
+                _ => word.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -32,6 +32,30 @@ fn empty_description() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn numbers_exercises_after_games_without_exercise() -> io::Result<()> {
+    let pgn = b"
+[White \"player1\"]
+[Black \"player2\"]
+
+1. e4 *
+
+[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]
+
+*
+";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    let game = reader.read_game(&mut position_converter)?.unwrap();
+    assert_eq!(position_converter.get_converted_exercise(game), None);
+    let exercise = reader.read_game(&mut position_converter)?.unwrap();
+    let exercise = position_converter.get_converted_exercise(exercise).unwrap();
+
+    assert_eq!(exercise.number, 1);
+    assert!(exercise.description.starts_with("Exercise 1:\n"));
+    Ok(())
+}
+
 #[test]
 fn converts_single_exercise() -> io::Result<()> {
     let pgn = b"
@@ -200,7 +224,7 @@ King Hector1
 Black:
 King Hector8
 Solution:
-1. King Hector2 
+1. King Hector2
 and so the king moves
 ";
 
@@ -208,6 +232,51 @@ and so the king moves
     Ok(())
 }
 
+#[test]
+fn cleans_up_comments() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 {[%clk 1:03:12] [%eval 2.1] Better than 1.Kg2, since Rg8+ comes later. [%csl Gg8][%cal Rg1g8,Yh2g3]}
+Kg8 {[%emt 0:00:05] [%clk 0:00:00.4] [%eval #-3]}
+
+ 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(false, true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. King Hector2
+Time left: one hour, three minutes, 12 seconds. Evaluation: White is better by about two pawns. Better than 1. King Gustav2, since Rook Gustav8 check comes later. Highlighted squares: Gustav8 in green. Arrows: Gustav1 to Gustav8 in red, Hector2 to Gustav3 in yellow.
+1... King Gustav8
+Time left: no time. Evaluation: Black mates in 3.
+"
+    ));
+    Ok(())
+}
+
+#[test]
+fn reads_comments_that_are_not_utf8() -> io::Result<()> {
+    // A Latin-1 comment, as written by older tools.
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 {Sch\xf6n} 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    assert!(description.ends_with("Solution:\n1. King Hector2\n"));
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(false, true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    assert!(description.ends_with("Solution:\n1. King Hector2\nSch\u{fffd}n\n"));
+    Ok(())
+}
+
 #[test]
 fn parses_exercise_with_side_lines() -> io::Result<()> {
     let pgn = b"
@@ -229,13 +298,1269 @@ King Hector1
 Black:
 King Hector8
 Solution:
-1. King Hector2 
-(1. King Gustav2 )
-King Gustav8
-(1... King Hector7
-2. King Gustav3 )
+1. King Hector2
+Variation 1 for White's move 1:
+1. King Gustav2
+End of variation.
+Back to main line: 1... King Gustav8
+Variation 1 for Black's move 1:
+1... King Hector7
+2. King Gustav3
+End of variation.
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_nested_side_lines() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 (1. Kg2 Kg8 (1... Kh7 2. Kh3 (2. Kf3 Kg6 (2... Kh6)) Kh6)) (1. Rg2) Kg8
+
+ 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. King Hector2
+Variation 1 for White's move 1:
+1. King Gustav2 King Gustav8
+Sub-variation 1 for Black's move 1:
+1... King Hector7
+2. King Hector3
+Level 3 sub-variation 1 for White's move 2:
+2. King Felix3 King Gustav6
+Level 4 sub-variation 1 for Black's move 2:
+2... King Hector6
+End of level 4 sub-variation.
+End of level 3 sub-variation.
+Back to sub-variation: 2... King Hector6
+End of sub-variation.
+End of variation.
+Variation 2 for White's move 1:
+1. Rook Gustav2
+End of variation.
+Back to main line: 1... King Gustav8
+"
+    ));
+
+    // Deeper side lines are only left out when asked to.
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    position_converter.set_max_variation_depth(Some(3));
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(!description.contains("Level 4 sub-variation"));
+    assert!(description.contains("End of level 3 sub-variation."));
+    Ok(())
+}
+
+#[test]
+fn limits_side_lines() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 (1. Kg2 {The king walks up.} Kg8 (1... Kh7 2. Kh3)) (1. Rg2) Kg8
+
+ 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    position_converter.set_max_variation_depth(Some(1));
+    position_converter.set_max_variation_moves(Some(1));
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. King Hector2
+Variation 1 for White's move 1:
+1. King Gustav2
+End of variation.
+Variation 2 for White's move 1:
+1. Rook Gustav2
+End of variation.
+Back to main line: 1... King Gustav8
+"
+    ));
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    position_converter.set_max_variation_depth(None);
+    position_converter.set_max_variation_moves(None);
+    position_converter.set_only_commented_variations(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. King Hector2
+Variation 1 for White's move 1:
+1. King Gustav2 King Gustav8
+End of variation.
+Back to main line: 1... King Gustav8
+"
+    ));
+    Ok(())
+}
+
+#[test]
+fn lays_out_solutions() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 Kg8 (1... Kh7 2. Kg3) 2. Kg3
+
+ 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    position_converter.set_solution_layout(SolutionLayout::PlyPerLine);
+    position_converter.set_variation_style(VariationStyle::Indented);
+    position_converter.set_with_side_prefixes(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. White: King Hector2
+1... Black: King Gustav8
+  1... Black: King Hector7
+  2. White: King Gustav3
+2. White: King Gustav3
+"
+    ));
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    position_converter.set_solution_layout(SolutionLayout::Paragraph);
+    position_converter.set_variation_style(VariationStyle::Spoken);
+    position_converter.set_with_side_prefixes(false);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. King Hector2 King Gustav8 Variation 1 for Black's move 1: 1... King Hector7 2. King Gustav3 End of variation. Back to main line: 2. King Gustav3
+"
+    ));
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_compact_piece_list() -> io::Result<()> {
+    let pgn = b"
+[FEN \"r5k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Pawns: Felix2, Gustav2, Hector2; Rook: Ana1; King: Gustav1
+Black:
+Pawns: Felix7, Gustav7, Hector7; Rook: Ana8; King: Gustav8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_pawn_summaries() -> io::Result<()> {
+    let pgn = b"
+[FEN \"rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_piece_list_style(PieceListStyle::Summary);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Pawns on Ana2 through Hector2 except Eva2, and Eva4; Knights: Bela1, Gustav1; Bishops: Caesar1, Felix1; Rooks: Ana1, Hector1; Queen: David1; King: Eva1
+Black:
+Pawns on Ana7 through Hector7 except Caesar7, and Caesar5; Knights: Bela8, Gustav8; Bishops: Caesar8, Felix8; Rooks: Ana8, Hector8; Queen: David8; King: Eva8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_material_summary() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/5ppp/8/P7/8/2b5/6PP/3R2K1 b - - 0 1\"]
+
+[FEN \"4k3/p7/8/8/8/8/8/2B1KB2 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_material(true);
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let mut description = String::new();
+    while let Some(single_exercise) = reader.read_game(&mut position_converter)? {
+        description.push_str(&single_exercise);
+    }
+
+    let result = "Exercise 1:
+Black to move:
+Material: White has rook and three pawns against bishop and three pawns; White is up the exchange
+Imbalances: White has a passed pawn on Ana5
+Black:
+Pawns: Felix7, Gustav7, Hector7; Bishop: Caesar3; King: Gustav8
+White:
+Pawns: Gustav2, Hector2, Ana5; Rook: David1; King: Gustav1
+Exercise 2:
+White to move:
+Material: White has two bishops against pawn; White is ahead by five points
+Imbalances: White has the bishop pair; Black has a passed pawn on Ana7
+White:
+Bishops: Caesar1, Felix1; King: Eva1
+Black:
+Pawn: Ana7; King: Eva8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_material_summary_to_braille_in_words() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/5ppp/8/P7/8/2b5/6PP/3R2K1 b - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_material(true);
+    position_converter.set_braille(Some(BrailleFormat::Text));
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    // Pieces are listed with their letters, but the material prose names them in words.
+    let result = "Exercise 1:
+Black to move:
+Material: White has rook and three pawns
+  against bishop and three pawns; White
+  is up the exchange
+Imbalances: White has a passed pawn on
+  a5
+Black:
+K g8
+B c3
+P f7 g7 h7
+White:
+K g1
+R d1
+P g2 h2 a5
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_key_features() -> io::Result<()> {
+    let pgn = b"
+[FEN \"r3k3/p1p5/2p2n2/6B1/8/8/P5PP/4RK2 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_features(true);
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Pawns: Ana2, Gustav2, Hector2; Bishop: Gustav5; Rook: Eva1; King: Felix1
+Black:
+Pawns: Caesar6, Ana7, Caesar7; Knight: Felix6; Rook: Ana8; King: Eva8
+Key features:
+White isolated pawn: Ana2
+White passed pawns: Gustav2, Hector2
+Black isolated pawns: Caesar6, Ana7, Caesar7
+Black doubled pawns: Caesar6, Caesar7
+Black passed pawns: Caesar6, Caesar7
+Open files: Bela, David, Eva, Felix
+Half-open files for White: Caesar
+Half-open files for Black: Gustav, Hector
+Hanging: Black Knight Felix6
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_pieces_pinned_to_the_king() -> io::Result<()> {
+    let pgn = b"
+[FEN \"4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_features(true);
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Rook: Eva2; King: Eva1
+Black:
+Rook: Eva7; King: Eva8
+Key features:
+Pinned to the king: White Rook Eva2 by Black Rook Eva7
+Pinned to the king: Black Rook Eva7 by White Rook Eva2
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_square_hints() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/1Q6/2B3K1 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_square_hints(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Bishop Caesar1, dark square, diagonal Caesar1 to Hector6 and diagonal Ana3 to Caesar1
+Queen Bela2, dark square, long diagonal Ana1 to Hector8 and diagonal Ana3 to Caesar1, near the Ana1 corner
+King Gustav1, dark square, near the Hector1 corner
+Black:
+King Hector8, dark square, in the Hector8 corner
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_attacks() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/4p3/8/5N2/6P1/6K1 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_attacks(true);
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Pawn: Gustav2; Knight: Felix3; King: Gustav1
+Black:
+Pawn: Eva5; King: Hector8
+Attacks and defences:
+White:
+Pawn Gustav2 attacks Hector3; defended by King Gustav1
+Knight Felix3 attacks Eva1, David2, Hector2, David4, Hector4, Eva5 and Gustav5; threatens Pawn Eva5; defended by Pawn Gustav2
+King Gustav1 attacks Felix1, Hector1, Felix2 and Hector2
+Black:
+Pawn Eva5 attacks David4 and Felix4
+King Hector8 attacks Gustav7, Hector7 and Gustav8
 ";
 
     assert_eq!(&description[..], result);
     Ok(())
 }
+
+#[test]
+fn converts_exercise_with_legal_moves() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/5ppp/8/8/8/8/1n6/R3R1K1 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_legal_moves(true);
+    position_converter.set_piece_list_style(PieceListStyle::Compact);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Rooks: Ana1, Eva1; King: Gustav1
+Black:
+Pawns: Felix7, Gustav7, Hector7; Knight: Bela2; King: Gustav8
+Legal moves:
+Rook Ana1: Rook from Ana1 to Bela1, Rook from Ana1 to Caesar1, Rook from Ana1 to David1, Rook Ana2, Rook Ana3, Rook Ana4, Rook Ana5, Rook Ana6, Rook Ana7, Rook Ana8 checkmate
+Rook Eva1: Rook from Eva1 to Bela1, Rook from Eva1 to Caesar1, Rook from Eva1 to David1, Rook Felix1, Rook Eva2, Rook Eva3, Rook Eva4, Rook Eva5, Rook Eva6, Rook Eva7, Rook Eva8 checkmate
+King Gustav1: King Felix1, King Hector1, King Felix2, King Gustav2, King Hector2
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_legal_moves_from_fen() {
+    let position_converter = PositionConverter::new();
+    let description = position_converter
+        .describe_legal_moves("8/6P1/8/8/8/8/8/K5k1 w - - 0 1")
+        .unwrap();
+
+    assert_eq!(
+        &description[..],
+        "Pawn Gustav7: Gustav8 promotes to Knight, Gustav8 promotes to Bishop, Gustav8 promotes to Rook check, Gustav8 promotes to Queen check
+King Ana1: King Bela1, King Ana2, King Bela2
+"
+    );
+    assert_eq!(
+        position_converter
+            .describe_legal_moves("not a fen")
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+}
+
+// A fake UCI engine that always reports the same evaluation and best line.
+const FAKE_ENGINE: &str = "
+while read command; do
+  case \"$command\" in
+    uci) echo 'id name Fake'; echo 'uciok' ;;
+    isready) echo 'readyok' ;;
+    go*)
+      echo 'info depth 1 score cp 20 pv h1h2'
+      echo 'info depth 2 score mate 1 nodes 42 pv a1a8'
+      echo 'bestmove a1a8' ;;
+    quit) exit 0 ;;
+  esac
+done
+";
+
+#[cfg(unix)]
+#[test]
+fn converts_exercise_with_engine_analysis() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/6K1/8/8/8/8/R7 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_engine(Engine::start("sh", &["-c", FAKE_ENGINE], 2)?);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+White to move:
+White:
+Rook Ana1
+King Gustav6
+Black:
+King Hector8
+Engine: White mates in 1; best line: 1. Rook Ana8 checkmate
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_engine_evaluations_from_whites_point_of_view() {
+    let position_converter = PositionConverter::new();
+    let board = Board::from_fen("7k/8/6K1/8/8/8/8/6R1 b - - 0 1").unwrap();
+    let analysis = Analysis {
+        score: EngineScore::Centipawns(-310),
+        principal_variation: vec![String::from("h8g8"), String::from("g1a1")],
+    };
+
+    assert_eq!(
+        position_converter.describe_analysis(&board, &analysis),
+        "White is winning by about three pawns; best line: 1... King Gustav8 2. Rook Ana1"
+    );
+}
+
+#[test]
+fn describes_mate_on_the_board() {
+    let position_converter = PositionConverter::new();
+    let board = Board::from_fen("R6k/6pp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+    let analysis = Analysis {
+        score: EngineScore::Mate(0),
+        principal_variation: vec![],
+    };
+
+    assert_eq!(
+        position_converter.describe_analysis(&board, &analysis),
+        "checkmate on the board"
+    );
+}
+
+// A fake UCI engine that keeps searching until it is told to stop.
+const SLOW_ENGINE: &str = "
+while read command; do
+  case \"$command\" in
+    uci) echo 'uciok' ;;
+    isready) echo 'readyok' ;;
+    go*) echo 'info depth 1 score cp 20 pv a1a8' ;;
+    stop) echo 'bestmove a1a8' ;;
+    quit) exit 0 ;;
+  esac
+done
+";
+
+#[cfg(unix)]
+#[test]
+fn stops_engine_after_its_time_limit() -> io::Result<()> {
+    let mut engine = Engine::start("sh", &["-c", SLOW_ENGINE], 30)?;
+    engine.set_time_limit(std::time::Duration::from_millis(200));
+    let analysis = engine.analyse("7k/8/6K1/8/8/8/8/R7 w - - 0 1")?;
+
+    assert_eq!(analysis.score, EngineScore::Centipawns(20));
+    assert_eq!(analysis.principal_variation, vec![String::from("a1a8")]);
+    Ok(())
+}
+
+#[test]
+fn verifies_exercise_solutions() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1\"]
+
+1. Re8# 1-0
+
+[FEN \"6k1/5ppp/8/8/8/8/8/R3R1K1 w - - 0 1\"]
+
+1. Re8+ Rxe8 1-0
+
+[FEN \"6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1\"]
+
+1. Kg2 1-0
+
+[FEN \"6k1/5ppp/8/8/8/8/8/4R1K1 w - - 0 1\"]
+
+1. Rh8+ 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_verify(true);
+    position_converter.set_mate_search_depth(2);
+    let mut report = String::new();
+    while let Some(single_exercise) = reader.read_game(&mut position_converter)? {
+        report.push_str(&single_exercise);
+    }
+
+    let result = "Exercise 2: ply 2 of the solution, Rook takes Eva8, is not legal; other first moves also mate in 1: Rook Ana8 checkmate; the solution does not end in mate
+Exercise 3: the first move King Gustav2 does not mate in 1, but Rook Eva8 checkmate does; the solution does not end in mate
+Exercise 4: ply 1 of the solution, Rook Hector8, is not legal
+";
+
+    assert_eq!(&report[..], result);
+    Ok(())
+}
+
+#[test]
+fn computes_solutions_for_mate_exercises() -> io::Result<()> {
+    let pgn = b"
+[FEN \"k7/8/1K6/8/8/8/8/7R w - - 0 1\"]
+
+*
+
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+*
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_with_computed_solutions(true);
+    position_converter.set_mate_search_depth(2);
+    let mut description = String::new();
+    while let Some(single_exercise) = reader.read_game(&mut position_converter)? {
+        description.push_str(&single_exercise);
+    }
+
+    let result = "Exercise 1:
+White to move:
+White:
+Rook Hector1
+King Bela6
+Black:
+King Ana8
+Computed solution, mate in 1:
+1. Rook Hector8 checkmate
+Exercise 2:
+White to move:
+White:
+Rook Gustav1
+King Hector1
+Black:
+King Hector8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_puzzle_headers() -> io::Result<()> {
+    let pgn = b"
+[Event \"Club puzzles\"]
+[Site \"?\"]
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+[Goal \"Mate in 3\"]
+[Themes \"fork pin mateIn3\"]
+[Rating \"1850\"]
+[Title \"GM\"]
+[Avoid \"Kh2\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    // Title and Avoid are ordinary pgn headers here, not the EPD id and am opcodes.
+    position_converter.set_extra_headers(vec![String::from("Event"), String::from("Round")]);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+Goal: mate in 3; Themes: fork, pin, mate in 3; Rating 1850; Event: Club puzzles
+White to move:
+White:
+Rook Gustav1
+King Hector1
+Black:
+King Hector8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_lichess_puzzles() -> io::Result<()> {
+    let csv = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00001,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,1200,80,90,100,backRankMate mateIn1 short,https://lichess.org/abcdef#1,
+00002,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,2500,80,90,100,backRankMate mateIn1 short,https://lichess.org/abcdef#1,
+00003,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,1300,80,90,100,endgame short,https://lichess.org/abcdef#1,
+";
+    let mut position_converter = PositionConverter::new();
+    let filter = LichessFilter {
+        min_rating: None,
+        max_rating: Some(2000),
+        themes: vec![String::from("mateIn1")],
+    };
+    let description = position_converter
+        .convert_lichess_csv(csv, &filter)?
+        .iter()
+        .map(|exercise| exercise.description.clone())
+        .collect::<String>();
+
+    let result = "Exercise 1:
+Themes: back rank mate, mate in 1, short; Rating 1200
+White to move:
+White:
+Pawn Felix2
+Pawn Gustav2
+Pawn Hector2
+Rook Ana1
+King Gustav1
+Black:
+Pawn Felix7
+Pawn Gustav7
+Pawn Hector7
+King Hector8
+Solution:
+2. Rook Ana8
+";
+
+    // The puzzle starts after the first move of Black, so its solution is on move 2.
+    assert_eq!(&description[..], result);
+
+    // Moves keep their check and mate suffix, E.G. for braille.
+    position_converter.set_braille(Some(BrailleFormat::Text));
+    let description = position_converter
+        .convert_lichess_csv(csv, &filter)?
+        .iter()
+        .map(|exercise| exercise.description.clone())
+        .collect::<String>();
+    assert!(description.ends_with("2. Ra8#\n"));
+    Ok(())
+}
+
+#[test]
+fn skips_bad_lichess_rows() -> io::Result<()> {
+    let csv = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags
+00001,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,1200,80,90,100,mateIn1,https://lichess.org/abcdef#1,
+00002,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a9,1200,80,90,100,mateIn1,https://lichess.org/abcdef#1,
+00003,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8
+00004,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,high,80,90,100,mateIn1,https://lichess.org/abcdef#1,
+00005,6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 0 1,g8h8 a1a8,1300,80,90,100,mateIn1,https://lichess.org/abcdef#1,
+";
+    let mut position_converter = PositionConverter::new();
+    let exercises = position_converter.convert_lichess_csv(csv, &LichessFilter::default())?;
+
+    // The bad rows in between leave no gap in the numbering.
+    let numbers: Vec<usize> = exercises.iter().map(|exercise| exercise.number).collect();
+    assert_eq!(numbers, vec![1, 2]);
+    assert!(exercises[1]
+        .description
+        .starts_with("Exercise 2:\nThemes: mate in 1; Rating 1300\n"));
+    Ok(())
+}
+
+#[test]
+fn converts_epd_positions() -> io::Result<()> {
+    let epd = "7k/6pp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"Back rank.001\";
+7k/8/8/8/8/8/8/6RK w - - 0 1
+6k1/5ppp/8/8/8/8/5PPP/R5K1  w  -   -  am Kf1 Kh1;  bm Ra8#;
+";
+    let mut position_converter = PositionConverter::new();
+    let description = position_converter
+        .convert_epd(epd)?
+        .iter()
+        .map(|exercise| exercise.description.clone())
+        .collect::<String>();
+
+    let result = "Exercise 1: Back rank.001
+White to move:
+White:
+Rook Ana1
+King Gustav1
+Black:
+Pawn Gustav7
+Pawn Hector7
+King Hector8
+Solution:
+1. Rook Ana8
+Exercise 2:
+White to move:
+White:
+Rook Gustav1
+King Hector1
+Black:
+King Hector8
+Exercise 3:
+Avoid: King Felix1 or King Hector1
+White to move:
+White:
+Pawn Felix2
+Pawn Gustav2
+Pawn Hector2
+Rook Ana1
+King Gustav1
+Black:
+Pawn Felix7
+Pawn Gustav7
+Pawn Hector7
+King Gustav8
+Solution:
+1. Rook Ana8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_uci_moves() -> io::Result<()> {
+    let position_converter = PositionConverter::new();
+    let fen = "k7/4P3/8/8/8/8/8/R3K2R w KQ - 0 1";
+    let descriptions =
+        position_converter.describe_uci_moves(fen, &["e1g1", "a8b7", "e7e8q", "b7b6", "a1d1"])?;
+
+    assert_eq!(
+        descriptions,
+        vec![
+            "Short Castling",
+            "King Bela7",
+            "Eva8 promotes to Queen",
+            "King Bela6",
+            "Rook from Ana1 to David1"
+        ]
+    );
+    assert!(position_converter
+        .describe_uci_moves(fen, &["e1e3"])
+        .is_err());
+
+    // Moves read like in the legal move list and engine lines, whatever the verbosity.
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_verbosity(Verbosity::Verbose);
+    assert_eq!(
+        position_converter.describe_uci_moves(fen, &["e7e8q"])?,
+        vec!["White pawn moves from Eva7 to Eva8, and promotes to queen, check"]
+    );
+    Ok(())
+}
+
+#[test]
+fn exports_annotated_pgn() -> io::Result<()> {
+    let pgn = b"
+[Event \"Club puzzles\"]
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 b - - 0 1\"]
+[Result \"1-0\"]
+
+1... h6 {Loses} (1... g6 2. Ra8#) 2. Ra8+ $1 Kh7 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut annotator = PgnAnnotator::new(PositionConverter::new());
+    let annotated_pgn = reader.read_game(&mut annotator)?.unwrap();
+
+    let result = "[Event \"Club puzzles\"]
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 b - - 0 1\"]
+[Result \"1-0\"]
+
+{Exercise 1:
+Black to move:
+Black:
+Pawn Gustav7
+Pawn Hector7
+King Hector8
+White:
+Rook Ana1
+King Gustav1}
+1... h6 {Hector6} {Loses} (1... g6 {Gustav6} 2. Ra8# {Rook Ana8}) 2. Ra8+ $1 {Rook Ana8} 2... Kh7 {King Hector7} 1-0
+
+";
+
+    assert_eq!(&annotated_pgn[..], result);
+
+    // Moves are described like in the converted exercises, and comments with braces are kept as they are.
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 w - - 0 1\"]
+[Goal \"Mate in {one}\"]
+
+1. Ra8# 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_move_origin(MoveOrigin::Always);
+    let mut annotator = PgnAnnotator::new(position_converter);
+    let annotated_pgn = reader.read_game(&mut annotator)?.unwrap();
+
+    assert!(annotated_pgn.contains("\n;Exercise 1:\n;Goal: mate in {one}\n;White to move:\n"));
+    assert!(annotated_pgn.ends_with("1. Ra8# {Rook from Ana1 to Ana8} 1-0\n\n"));
+    Ok(())
+}
+
+#[test]
+fn numbers_moves_from_the_fen() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 b - - 0 23\"]
+
+23... h6 24. Ra8+ Kh7 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with("Solution:\n23... Hector6\n24. Rook Ana8 King Hector7\n"));
+
+    // Annotated pgn follows the same numbering.
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut annotator = PgnAnnotator::new(PositionConverter::new());
+    let annotated_pgn = reader.read_game(&mut annotator)?.unwrap();
+
+    assert!(
+        annotated_pgn.contains("23... h6 {Hector6} 24. Ra8+ {Rook Ana8} 24... Kh7 {King Hector7}")
+    );
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_to_ssml() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 w - - 0 1\"]
+[Themes \"backRankMate\"]
+
+1. Ra8# 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_ssml(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "<speak>
+<p><s>Exercise 1:</s><s>Themes: back rank mate</s><s>White to move:</s></p>
+<prosody rate=\"slow\"><p><s>White:</s><s>Rook Ana 1</s><s>King Gustav 1</s><s>Black:</s><s>Pawn Gustav 7</s><s>Pawn Hector 7</s><s>King Hector 8</s></p></prosody>
+<p><s>Solution:</s>
+<say-as interpret-as=\"cardinal\">1</say-as> Rook Ana 8 <emphasis level=\"strong\">checkmate</emphasis><break time=\"600ms\"/>
+</p>
+</speak>
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn converts_side_lines_to_ssml_sentences() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/8/6RK w - - 0 1\"]
+
+1. Kh2 (1. Kg2) Kg8 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    position_converter.set_ssml(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.contains("<s>Variation 1 for White&apos;s move 1:</s>"));
+    assert!(description.contains("<s>End of variation.</s>"));
+    assert!(description.contains("<s>Back to main line:</s>"));
+    Ok(())
+}
+
+#[test]
+fn joins_ssml_exercises_into_one_document() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 w - - 0 1\"]
+
+1. Ra8# 1-0
+
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 w - - 0 1\"]
+
+1. Ra8# 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_ssml(true);
+    let mut documents: Vec<String> = vec![];
+    while let Some(description) = reader.read_game(&mut position_converter)? {
+        documents.push(description);
+    }
+    let document = join_ssml_documents(&documents);
+
+    assert!(document.starts_with("<speak>\n<p><s>Exercise 1:</s>"));
+    assert!(document.ends_with("</p>\n</speak>\n"));
+    assert_eq!(document.matches("<speak>").count(), 1);
+    assert!(document.contains("<s>Exercise 2:</s>"));
+    Ok(())
+}
+#[test]
+fn converts_verbose_exercise_to_ssml() -> io::Result<()> {
+    let pgn = b"
+[FEN \"r6k/8/8/8/8/8/5PPP/6K1 b - - 0 1\"]
+
+1... Ra1# 0-1
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_ssml(true);
+    position_converter.set_verbosity(Verbosity::Verbose);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    // The verbose move already says the side and the mate, so they are not added again.
+    let result = "<p><s>Solution, move by move:</s>
+<say-as interpret-as=\"cardinal\">1</say-as> Black rook moves from Ana 8 to Ana 1, <emphasis level=\"strong\">checkmate</emphasis><break time=\"600ms\"/>
+</p>";
+    assert!(description.contains(result), "{}", description);
+    Ok(())
+}
+
+// The stand-in for a text-to-speech command is a shell script.
+#[cfg(unix)]
+#[test]
+fn exports_exercises_to_audio() -> io::Result<()> {
+    let directory =
+        std::env::temp_dir().join(format!("blindfold-chess-audio-{}", std::process::id()));
+    std::fs::create_dir_all(&directory)?;
+    // Exercise 2 was left out, E.G. because it had no issues in verify mode.
+    let exercises = vec![
+        ConvertedExercise {
+            number: 1,
+            description: String::from("Exercise 1:\nWhite to move:\nSolution:\n1. Rook Ana8\n"),
+            heading_lines: vec![2],
+            solution_line: Some(2),
+        },
+        ConvertedExercise {
+            number: 3,
+            description: String::from("Exercise 3:\nBlack to move:\n"),
+            heading_lines: vec![],
+            solution_line: None,
+        },
+    ];
+    // A stand-in for a text-to-speech command that writes the text it reads as the audio file.
+    let mut audio_exporter = AudioExporter::new("sh", &["-c", "cat > \"$1\"", "sh", "{output}"]);
+    audio_exporter.set_separate_solutions(true);
+    audio_exporter.export(&exercises, &directory.join("exercises.m3u"))?;
+
+    let playlist = std::fs::read_to_string(directory.join("exercises.m3u"))?;
+    let position = std::fs::read_to_string(directory.join("exercise-001.wav"))?;
+    let solution = std::fs::read_to_string(directory.join("exercise-001-solution.wav"))?;
+    std::fs::remove_dir_all(&directory)?;
+
+    let result = "#EXTM3U
+#EXTINF:-1,Exercise 1
+exercise-001.wav
+#EXTINF:-1,Exercise 1, solution
+exercise-001-solution.wav
+#EXTINF:-1,Exercise 3
+exercise-003.wav
+";
+    assert_eq!(&playlist[..], result);
+    assert_eq!(&position[..], "Exercise 1:\nWhite to move:\n");
+    assert_eq!(&solution[..], "Solution:\n1. Rook Ana8\n");
+    Ok(())
+}
+
+#[test]
+fn splits_solution_of_ssml_and_braille_exercises() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/8/R5K1 w - - 0 1\"]
+
+1. Ra8# 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_ssml(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    let exercise = position_converter
+        .get_converted_exercise(description)
+        .unwrap();
+    let (position, solution) = audio::split_solution(&exercise);
+
+    assert!(position.starts_with("<speak>\n<p><s>Exercise 1:</s>"));
+    assert!(position.ends_with("</prosody>\n</speak>\n"));
+    assert!(solution
+        .unwrap()
+        .starts_with("<speak>\n<p><s>Solution:</s>\n"));
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_braille(Some(BrailleFormat::Text));
+    position_converter.set_braille_line_width(12);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    let exercise = position_converter
+        .get_converted_exercise(description)
+        .unwrap();
+    let (position, solution) = audio::split_solution(&exercise);
+
+    assert!(position.starts_with("Exercise 1:\n"));
+    assert!(!position.contains("Solution"));
+    assert!(solution.unwrap().starts_with("Solution:\n"));
+    Ok(())
+}
+
+#[test]
+fn exports_exercises_to_epub() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]
+
+1. Ra8# 1-0
+
+[FEN \"7k/6pp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]
+
+1. Ra8# (1. Re1 h6) 1-0
+";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    reader.read_game(&mut position_converter)?;
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    // Only the second exercise is in the book, E.G. because the first one had no issues in verify mode.
+    let exercises = vec![position_converter
+        .get_converted_exercise(description)
+        .unwrap()];
+    let book = EpubExporter::new("Back rank").build(&exercises);
+    let book_str = String::from_utf8_lossy(&book);
+
+    // EPUB readers expect the uncompressed mimetype as the first file of the archive.
+    assert_eq!(&book[..4], b"PK\x03\x04");
+    assert_eq!(&book[30..58], b"mimetypeapplication/epub+zip");
+    assert!(book_str.contains(
+        "<h1>Exercise 2</h1>\n<p>White to move:</p>\n<h2>White</h2>\n<p>Pawn Felix2</p>"
+    ));
+    assert!(book_str.contains("<p><a href=\"solutions.xhtml#solution-2\">Solution</a></p>"));
+    assert!(book_str.contains("<section id=\"solution-2\">\n<h2>Exercise 2</h2>\n<h3>Solution</h3>\n<p>1. Rook Ana8</p>\n"));
+    // Variation markers end with a colon, but only the sections of the description are headings.
+    assert!(
+        book_str.contains("<p>Variation 1 for White's move 1:</p>\n<p>1. Rook Eva1 Hector6</p>")
+    );
+    assert!(book_str.contains("<p><a href=\"exercise-002.xhtml\">Back to the exercise</a></p>"));
+    assert!(book_str.contains("<navPoint id=\"nav-1\" playOrder=\"1\"><navLabel><text>Exercise 2</text></navLabel><content src=\"exercise-002.xhtml\"/></navPoint>"));
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_to_braille() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/6pp/8/8/8/8/5PPP/R5K1 w - - 0 1\"]
+[Themes \"backRankMate mateIn1\"]
+
+1. Ra8# 1-0
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_braille(Some(BrailleFormat::Text));
+    position_converter.set_braille_line_width(20);
+    position_converter.set_with_legal_moves(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+Themes: back rank
+  mate, mate in 1
+White to move:
+White:
+K g1
+R a1
+P f2 g2 h2
+Black:
+K h8
+P g7 h7
+Legal moves:
+P f2: f3, f4
+P g2: g3, g4
+P h2: h3, h4
+R a1: Rb1, Rc1, Rd1,
+  Re1, Rf1, Ra2,
+  Ra3, Ra4, Ra5,
+  Ra6, Ra7, Ra8#
+K g1: Kf1, Kh1
+Solution:
+1. Ra8#
+";
+
+    assert_eq!(&description[..], result);
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    position_converter.set_braille(Some(BrailleFormat::Unicode));
+    // The converter keeps counting exercises, so this one is "Exercise 2:".
+    position_converter.set_with_legal_moves(false);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+    assert!(description.starts_with("⠑⠭⠑⠗⠉⠊⠎⠑⠀⠆⠱\n"));
+    Ok(())
+}
+
+#[test]
+fn converts_exercise_with_verbosity_levels() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/5ppp/8/8/8/5n2/8/R5K1 b - - 0 1\"]
+
+1... Nd2 (1... Nh2 2. Kxh2) 2. Ra8# 0-1
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new_with_config(true, false);
+    position_converter.set_verbosity(Verbosity::Verbose);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+Black to move, in the following position:
+Black pieces:
+Black pawn on Felix7
+Black pawn on Gustav7
+Black pawn on Hector7
+Black knight on Felix3
+Black king on Gustav8
+White pieces:
+White rook on Ana1
+White king on Gustav1
+Solution, move by move:
+1... Black knight moves from Felix3 to David2
+Variation 1 for Black's move 1:
+1... Black knight moves from Felix3 to Hector2
+2. White king moves from Gustav1 to Hector2, capturing the Black knight
+End of variation.
+Back to main line: 2. White rook moves from Ana1 to Ana8, checkmate
+";
+
+    assert_eq!(&description[..], result);
+
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    position_converter.set_verbosity(Verbosity::Terse);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 2:
+B to move:
+B:
+Pawn f7
+Pawn g7
+Pawn h7
+Knight f3
+King g8
+W:
+Rook a1
+King g1
+Solution:
+1... Knight d2
+Variation 1 for Black's move 1:
+1... Knight h2
+2. King takes h2
+End of variation.
+Back to main line: 2. Rook a8
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn shortens_terse_headings() -> io::Result<()> {
+    let pgn = b"
+[FEN \"6k1/8/8/8/8/8/4P3/6K1 w - - 0 1\"]
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_verbosity(Verbosity::Terse);
+    position_converter.set_with_features(true);
+    position_converter.set_with_attacks(true);
+    position_converter.set_with_legal_moves(true);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    let result = "Exercise 1:
+W to move:
+W:
+Pawn e2
+King g1
+B:
+King g8
+Features:
+White isolated pawn: e2
+White passed pawn: e2
+Open files: a, b, c, d, f, g, h
+Half-open files for Black: e
+Attacks:
+W:
+Pawn e2 attacks d3 and f3
+King g1 attacks f1, h1, f2, g2 and h2
+B:
+King g8 attacks f7, g7, h7, f8 and h8
+Moves:
+Pawn e2: e3, e4
+King g1: King f1, King h1, King f2, King g2, King h2
+";
+
+    assert_eq!(&description[..], result);
+    Ok(())
+}
+
+#[test]
+fn describes_move_origins() -> io::Result<()> {
+    let pgn = b"
+[FEN \"7k/8/8/8/8/8/4P3/1N2K1N1 w - - 0 1\"]
+
+1. Nd2 Kh7 2. e4 *
+        ";
+    let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_move_origin(MoveOrigin::Always);
+    let description = reader.read_game(&mut position_converter)?.unwrap();
+
+    assert!(description.ends_with(
+        "Solution:
+1. Knight from Bela1 to David2 King from Hector8 to Hector7
+2. Pawn from Eva2 to Eva4
+"
+    ));
+    Ok(())
+}
+
+#[test]
+fn describes_san_disambiguation() {
+    let position_converter = PositionConverter::new();
+    let describe = |san: &[u8]| position_converter.describe_san(&San::from_ascii(san).unwrap());
+
+    assert_eq!(describe(b"Rae1"), "Rook from the Ana file to Eva1");
+    assert_eq!(describe(b"R5g7"), "Rook from rank 5 to Gustav7");
+    assert_eq!(describe(b"Qh4xe1"), "Queen from Hector4 takes Eva1");
+    assert_eq!(describe(b"exd5"), "Eva takes David5");
+
+    let mut position_converter = PositionConverter::new();
+    position_converter.set_verbosity(Verbosity::Terse);
+    assert_eq!(
+        position_converter.describe_san(&San::from_ascii(b"Rae1").unwrap()),
+        "Rook from the a file to e1"
+    );
+}
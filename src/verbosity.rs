@@ -0,0 +1,189 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Phrasing for the verbosity levels, from "Rook g1" for experts to "White rook on Gustav1" for beginners.
+
+use super::moves::find_san_move;
+use super::PositionConverter;
+use pgn_reader::{CastlingSide, San};
+use std::fmt::Write;
+use tanton::board::Board;
+use tanton::core::Player;
+
+// How much is said about pieces, moves and sections.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verbosity {
+    // Piece names with algebraic squares and short headings, E.G. "Rook g1", "Rook takes g8" and "W:".
+    Terse,
+    // Piece names with phonetic files, E.G. "Rook Gustav1" and "Rook takes Gustav8".
+    Normal,
+    // Full sentences with colours and origin squares, E.G. "White rook moves from Gustav1 to Gustav8, check".
+    Verbose,
+}
+
+// The section headers of a position description.
+pub(crate) enum Heading<'a> {
+    // The side to move, E.G. "White".
+    SideToMove(&'a str),
+    // The pieces of a side, E.G. "Black".
+    Pieces(&'a str),
+    Features,
+    Attacks,
+    LegalMoves,
+    Solution,
+    // A solution found by the mate search, with its number of moves.
+    ComputedSolution(usize),
+}
+
+impl PositionConverter {
+    pub(crate) fn describe_heading(&self, heading: Heading) -> String {
+        let verbose = self.verbosity == Verbosity::Verbose;
+        let terse = self.verbosity == Verbosity::Terse;
+        match heading {
+            // Sides are shortened to their initial, E.G. "W to move:" and "B:".
+            Heading::SideToMove(side) if terse => format!("{} to move:", &side[..1]),
+            Heading::Pieces(side) if terse => format!("{}:", &side[..1]),
+            Heading::Features if terse => String::from("Features:"),
+            Heading::Attacks if terse => String::from("Attacks:"),
+            Heading::LegalMoves if terse => String::from("Moves:"),
+            Heading::SideToMove(side) if verbose => {
+                format!("{} to move, in the following position:", side)
+            }
+            Heading::SideToMove(side) => format!("{} to move:", side),
+            Heading::Pieces(side) if verbose => format!("{} pieces:", side),
+            Heading::Pieces(side) => format!("{}:", side),
+            Heading::Solution if verbose => String::from("Solution, move by move:"),
+            Heading::Features => String::from("Key features:"),
+            Heading::Attacks => String::from("Attacks and defences:"),
+            Heading::LegalMoves => String::from("Legal moves:"),
+            Heading::Solution => String::from("Solution:"),
+            Heading::ComputedSolution(moves) => format!("Computed solution, mate in {}:", moves),
+        }
+    }
+
+    // Describes a listed piece in full, E.G. "White rook on Gustav1".
+    pub(crate) fn describe_verbose_piece(&self, piece_with_color: char, square: &str) -> String {
+        let color = if piece_with_color.is_uppercase() {
+            "White"
+        } else {
+            "Black"
+        };
+        let piece = piece_with_color.to_lowercase().to_string();
+        format!(
+            "{} {} on {}",
+            color,
+            self.get_piece_name(&piece).to_lowercase(),
+            square
+        )
+    }
+
+    // Describes a move of |board| in a full sentence, E.G. "Black knight moves from Felix6 to Eva4, capturing the White
+    // pawn". Without a board, E.G. after an illegal move, |white| tells whose move it is and the origin is left out.
+    pub(crate) fn describe_verbose_move(
+        &self,
+        board: Option<&Board>,
+        san: &San,
+        white: bool,
+    ) -> String {
+        let bit_move = board.and_then(|board| find_san_move(board, san));
+        let color = match board {
+            Some(board) => self.get_player_name(board.turn()),
+            None if white => self.get_player_name(Player::White),
+            None => self.get_player_name(Player::Black),
+        };
+        let mut move_description = String::new();
+        match *san {
+            San::Normal {
+                role,
+                capture,
+                to,
+                promotion,
+                ..
+            } => {
+                write!(
+                    move_description,
+                    "{} {} moves ",
+                    color,
+                    self.get_piece_name(&role.char().to_string()).to_lowercase()
+                )
+                .unwrap();
+                if let Some(bit_move) = bit_move {
+                    write!(
+                        move_description,
+                        "from {} ",
+                        self.describe_square(&bit_move.get_src())
+                    )
+                    .unwrap();
+                }
+                write!(
+                    move_description,
+                    "to {}{}",
+                    self.get_file_name(to.file() as u8),
+                    to.rank().char()
+                )
+                .unwrap();
+                if capture {
+                    match (board, bit_move) {
+                        (Some(board), Some(bit_move)) if !bit_move.is_en_passant() => {
+                            let captured = board.piece_at_sq(bit_move.get_dest());
+                            write!(
+                                move_description,
+                                ", capturing the {} {}",
+                                self.get_player_name(!board.turn()),
+                                self.get_piece_name(&captured.type_of().char_lower().to_string())
+                                    .to_lowercase()
+                            )
+                            .unwrap();
+                        }
+                        (Some(board), Some(_)) => write!(
+                            move_description,
+                            ", capturing the {} pawn en passant",
+                            self.get_player_name(!board.turn())
+                        )
+                        .unwrap(),
+                        _ => write!(move_description, ", capturing").unwrap(),
+                    }
+                }
+                if let Some(promotion) = promotion {
+                    write!(
+                        move_description,
+                        ", and promotes to {}",
+                        self.get_piece_name(&promotion.char().to_string())
+                            .to_lowercase()
+                    )
+                    .unwrap();
+                }
+            }
+            San::Castle(CastlingSide::KingSide) => {
+                write!(move_description, "{} castles short", color).unwrap()
+            }
+            San::Castle(CastlingSide::QueenSide) => {
+                write!(move_description, "{} castles long", color).unwrap()
+            }
+            _ => write!(move_description, "{} passes", color).unwrap(),
+        }
+        if let (Some(board), Some(bit_move)) = (board, bit_move) {
+            let mut board_after_move = board.shallow_clone();
+            board_after_move.apply_move(bit_move);
+            if board_after_move.checkmate() {
+                write!(move_description, ", checkmate").unwrap();
+            } else if board_after_move.in_check() {
+                write!(move_description, ", check").unwrap();
+            }
+        }
+        move_description
+    }
+}
@@ -0,0 +1,111 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// Checks the main line of exercises with the built-in mate search and, if configured, a UCI engine.
+
+use super::engine::EngineScore;
+use super::mate::{find_mate, find_mating_moves, forces_mate};
+use super::moves::find_san_move;
+use super::{join_with_and, PositionConverter};
+use tanton::board::Board;
+use tanton::core::piece_move::BitMove;
+
+impl PositionConverter {
+    // Returns a report line for the current exercise, E.G.
+    // "Exercise 3: the solution does not end in mate", or an empty string if nothing looks wrong.
+    pub(crate) fn verify_exercise(&mut self, board: &Board) -> String {
+        let mut issues: Vec<String> = vec![];
+        let mut final_board = board.shallow_clone();
+        let mut solution: Vec<BitMove> = vec![];
+        for (index, san) in self.main_line.iter().enumerate() {
+            match find_san_move(&final_board, san) {
+                Some(bit_move) => {
+                    final_board.apply_move(bit_move);
+                    solution.push(bit_move);
+                }
+                None => {
+                    issues.push(format!(
+                        "ply {} of the solution, {}, is not legal",
+                        index + 1,
+                        self.describe_san(san)
+                    ));
+                    break;
+                }
+            }
+        }
+        let ends_in_mate = final_board.checkmate() && solution.len() == self.main_line.len();
+        let mut reported_missing_mate = false;
+        if self.main_line.is_empty() {
+            issues.push(String::from("there is no solution"));
+        }
+        if let (Some(first_move), Some(mate)) =
+            (solution.first(), find_mate(board, self.mate_search_depth))
+        {
+            let mate_moves = mate.len().div_ceil(2);
+            if !forces_mate(board, *first_move, mate_moves) {
+                issues.push(format!(
+                    "the first move {} does not mate in {}, but {} does",
+                    self.describe_bit_move(board, *first_move),
+                    mate_moves,
+                    self.describe_bit_move(board, mate[0])
+                ));
+            } else {
+                let alternatives: Vec<String> = find_mating_moves(board, mate_moves)
+                    .into_iter()
+                    .filter(|alternative| alternative != first_move)
+                    .map(|alternative| self.describe_bit_move(board, alternative))
+                    .collect();
+                if !alternatives.is_empty() {
+                    issues.push(format!(
+                        "other first moves also mate in {}: {}",
+                        mate_moves,
+                        join_with_and(&alternatives)
+                    ));
+                }
+            }
+            if !ends_in_mate {
+                issues.push(String::from("the solution does not end in mate"));
+                reported_missing_mate = true;
+            }
+        }
+        let fen = board.fen();
+        let analysis = self.engine.as_mut().map(|engine| engine.analyse(&fen));
+        match analysis {
+            Some(Ok(analysis)) => {
+                let best_move = &analysis.principal_variation[0];
+                if let Some(first_move) = solution.first() {
+                    if first_move.stringify() != *best_move {
+                        issues.push(format!(
+                            "the engine prefers {}",
+                            self.describe_analysis(board, &analysis)
+                        ));
+                    }
+                }
+                if let EngineScore::Mate(moves) = analysis.score {
+                    if moves > 0 && !ends_in_mate && !reported_missing_mate {
+                        issues.push(String::from("the solution does not end in mate"));
+                    }
+                }
+            }
+            Some(Err(error)) => issues.push(format!("engine analysis failed, {}", error)),
+            None => {}
+        }
+        if issues.is_empty() {
+            return String::new();
+        }
+        format!("Exercise {}: {}\n", self.exercise_number, issues.join("; "))
+    }
+}
@@ -0,0 +1,119 @@
+// Apache header:
+//
+//     Copyright 2020 Google LLC
+//
+//     Licensed under the Apache License, Version 2.0 (the "License");
+//     you may not use this file except in compliance with the License.
+//     You may obtain a copy of the License at
+//
+//         https://www.apache.org/licenses/LICENSE-2.0
+//
+//     Unless required by applicable law or agreed to in writing, software
+//     distributed under the License is distributed on an "AS IS" BASIS,
+//     WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//     See the License for the specific language governing permissions and
+//     limitations under the License.
+
+// A minimal ZIP writer for packaging books, so no external tool or library is needed.
+// Files are stored without compression, which is what EPUB requires for its first file anyway.
+
+pub(crate) struct ZipWriter {
+    // The archive written so far: local file headers followed by file contents.
+    archive: Vec<u8>,
+    // The central directory entries, written at the end of the archive.
+    central_directory: Vec<u8>,
+    file_count: u16,
+}
+
+impl ZipWriter {
+    pub(crate) fn new() -> ZipWriter {
+        ZipWriter {
+            archive: vec![],
+            central_directory: vec![],
+            file_count: 0,
+        }
+    }
+
+    pub(crate) fn add_file(&mut self, name: &str, contents: &[u8]) {
+        let offset = self.archive.len() as u32;
+        let crc = crc32(contents);
+        let size = contents.len() as u32;
+        // Local file header.
+        push_u32(&mut self.archive, 0x0403_4b50);
+        self.push_common_fields(crc, size, name, true);
+        self.archive.extend_from_slice(name.as_bytes());
+        self.archive.extend_from_slice(contents);
+        // Central directory entry.
+        push_u32(&mut self.central_directory, 0x0201_4b50);
+        // Version made by.
+        push_u16(&mut self.central_directory, 20);
+        self.push_common_fields(crc, size, name, false);
+        // Comment length, disk number, internal and external attributes.
+        push_u16(&mut self.central_directory, 0);
+        push_u16(&mut self.central_directory, 0);
+        push_u16(&mut self.central_directory, 0);
+        push_u32(&mut self.central_directory, 0);
+        push_u32(&mut self.central_directory, offset);
+        self.central_directory.extend_from_slice(name.as_bytes());
+        self.file_count += 1;
+    }
+
+    // Writes the fields shared by local file headers and central directory entries, up to the extra field length.
+    fn push_common_fields(&mut self, crc: u32, size: u32, name: &str, local: bool) {
+        let buffer = if local {
+            &mut self.archive
+        } else {
+            &mut self.central_directory
+        };
+        // Version needed to extract, flags and compression method (stored).
+        push_u16(buffer, 20);
+        push_u16(buffer, 0);
+        push_u16(buffer, 0);
+        // Modification time and date, fixed to 1980-01-01 so archives are reproducible.
+        push_u16(buffer, 0);
+        push_u16(buffer, (1 << 5) | 1);
+        push_u32(buffer, crc);
+        // Compressed and uncompressed sizes are equal for stored files.
+        push_u32(buffer, size);
+        push_u32(buffer, size);
+        push_u16(buffer, name.len() as u16);
+        push_u16(buffer, 0);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let central_directory_offset = self.archive.len() as u32;
+        let central_directory_size = self.central_directory.len() as u32;
+        self.archive.append(&mut self.central_directory);
+        // End of central directory record.
+        push_u32(&mut self.archive, 0x0605_4b50);
+        push_u16(&mut self.archive, 0);
+        push_u16(&mut self.archive, 0);
+        push_u16(&mut self.archive, self.file_count);
+        push_u16(&mut self.archive, self.file_count);
+        push_u32(&mut self.archive, central_directory_size);
+        push_u32(&mut self.archive, central_directory_offset);
+        push_u16(&mut self.archive, 0);
+        self.archive
+    }
+}
+
+fn push_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+// The CRC-32 checksum used by ZIP, computed bit by bit, which is fast enough for books.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data.iter() {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}